@@ -3,18 +3,27 @@ use std::path::Path;
 use std::process::Command;
 
 fn main() {
+  // The C shim only exists for the Unix `sys` backend - the Windows backend
+  // calls LockFileEx/UnlockFileEx directly, so there's nothing to compile.
+  if env::var_os("CARGO_CFG_UNIX").is_none() {
+    return;
+  }
+
   let out_dir = env::var("OUT_DIR").unwrap();
 
   Command::new("gcc")
-    .args(&["src/lock.c", "-c", "-fPIC", "-o"])
-    .arg(&format!("{}/lock.o", out_dir))
+    .args(["src/lock.c", "-c", "-fPIC", "-o"])
+    .arg(format!("{}/lock.o", out_dir))
     .status().unwrap();
 
   Command::new("ar")
-    .args(&["crus", "liblock.a", "lock.o"])
-    .current_dir(&Path::new(&out_dir))
+    .args(["crus", "liblock.a", "lock.o"])
+    .current_dir(Path::new(&out_dir))
     .status().unwrap();
 
     println!("cargo:rustc-link-search=native={}", out_dir);
     println!("cargo:rustc-link-lib=static=lock");
+    // lock.c's c_lock_range_timeout() uses pthread_create/pthread_kill to
+    // target the SIGALRM that bounds its wait at a specific thread.
+    println!("cargo:rustc-link-lib=dylib=pthread");
 }
@@ -0,0 +1,83 @@
+extern crate once_cell;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::mem;
+use std::fs::canonicalize;
+
+use self::once_cell::sync::OnceCell;
+
+use flock::{self, FileLock};
+use lock::{LockKind, AccessMode};
+
+fn registry() -> &'static Mutex<HashMap<PathBuf, Arc<Mutex<()>>>> {
+    static REGISTRY: OnceCell<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> = OnceCell::new();
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn process_mutex_for(key: &Path) -> Arc<Mutex<()>> {
+    let mut paths = registry().lock().unwrap();
+    paths.entry(key.to_path_buf()).or_insert_with(|| Arc::new(Mutex::new(()))).clone()
+}
+
+/// Best-effort canonicalization used to key the per-path mutex registry:
+/// two different-looking paths that name the same file should share one
+/// in-process mutex. Falls back to the path as given (e.g. the file may
+/// not exist yet) rather than failing the lock attempt outright.
+fn lock_key(path: &Path) -> PathBuf {
+    canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Obtains both an in-process mutex and a cross-process fcntl lock on
+/// `path`, returning a guard that only exists once both are held.
+///
+/// `fcntl` advisory locks are owned by the *process*, not the thread or
+/// file descriptor that took them - two threads in the same process
+/// calling `FileLock::lock` on the same path will *both* succeed, since as
+/// far as `fcntl` is concerned the process already holds the lock. This
+/// combines the existing fcntl lock with a process-global `Mutex` keyed by
+/// canonicalized path, so two threads that both try to lock the same file
+/// are also serialized against each other, not just against other
+/// processes.
+pub fn lock(path: PathBuf, mode: AccessMode, kind: LockKind) -> Result<CoordinatedGuard, flock::Error> {
+    let mutex = process_mutex_for(&lock_key(&path));
+
+    // `Mutex::lock` only returns an error if the mutex is poisoned, which
+    // would mean a prior holder panicked while holding it; we don't treat
+    // that as invalidating the data it protects, since it protects nothing
+    // but mutual exclusion itself.
+    let process_guard = mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    // Safe: `_mutex_keepalive` below keeps `mutex`'s `Mutex<()>` alive for
+    // at least as long as `process_guard` borrows it, and `CoordinatedGuard`
+    // drops `process_guard` before `_mutex_keepalive`.
+    let process_guard: MutexGuard<'static, ()> = unsafe { mem::transmute(process_guard) };
+
+    let mut file_lock = FileLock::new(path, mode);
+    file_lock.any_lock(kind)?;
+
+    Ok(CoordinatedGuard {
+        file_lock,
+        _process_guard: process_guard,
+        _mutex_keepalive: mutex,
+    })
+}
+
+/// Holds both the in-process mutex and the cross-process fcntl lock
+/// obtained by [`lock`](fn.lock.html), releasing both on `Drop`.
+pub struct CoordinatedGuard {
+    // Declaration order is drop order: the fcntl lock must be released
+    // before the in-process mutex, so a thread waiting on the mutex never
+    // observes it released while the file is still locked.
+    file_lock: FileLock,
+    _process_guard: MutexGuard<'static, ()>,
+    _mutex_keepalive: Arc<Mutex<()>>,
+}
+
+impl CoordinatedGuard {
+    /// The locked file, for the common lock-then-do-I/O pattern.
+    pub fn file_lock(&mut self) -> &mut FileLock {
+        &mut self.file_lock
+    }
+}
@@ -0,0 +1,96 @@
+use std::path::{Path, PathBuf};
+
+use dirlock;
+use flock::{self, FileLock};
+use lock;
+use fd;
+use filename;
+
+/// Options controlling how [`DirLock`](struct.DirLock.html) locks a directory.
+#[derive(Debug, Clone)]
+pub struct DirLockOptions {
+    /// Take an exclusive (single-writer) lock rather than a shared (multi-reader) one.
+    pub exclusive: bool,
+    /// Fail immediately instead of waiting if the lock is already held.
+    pub non_blocking: bool,
+    /// The name of the sentinel lock file created inside the locked directory.
+    pub file_name: String,
+}
+
+impl Default for DirLockOptions {
+    fn default() -> DirLockOptions {
+        DirLockOptions {
+            exclusive: true,
+            non_blocking: false,
+            file_name: ".lock".to_string(),
+        }
+    }
+}
+
+impl From<DirLockOptions> for dirlock::DirLockOptions {
+    fn from(options: DirLockOptions) -> dirlock::DirLockOptions {
+        dirlock::DirLockOptions {
+            exclusive: options.exclusive,
+            non_blocking: options.non_blocking,
+            lock_file_name: options.file_name,
+        }
+    }
+}
+
+/// A lock on an entire directory, rather than a single file.
+///
+/// A thin wrapper over [`dirlock::DirLock`](../dirlock/struct.DirLock.html) for callers already
+/// on the `filename`/`fd` track: it does the actual directory locking (see that type for the
+/// details), and this one only translates between its `lock`-flavoured options/errors and the
+/// `filename`-flavoured ones this module's callers expect.
+#[derive(Debug)]
+pub struct DirLock {
+    inner: dirlock::DirLock,
+}
+
+impl DirLock {
+    /// Locks `dir` according to `options`, creating `dir` (and any missing parents) if it
+    /// doesn't already exist.
+    pub fn new(dir: PathBuf, options: DirLockOptions) -> Result<DirLock, filename::Error> {
+        let path = dir.clone();
+        dirlock::DirLock::new(dir, options.into())
+                         .map(|inner| DirLock { inner })
+                         .map_err(|err| to_filename_error(path, err))
+    }
+
+    /// Take an exclusive lock on `dir` using the default sentinel file name, waiting for any
+    /// existing holder to release it.
+    pub fn exclusive(dir: PathBuf) -> Result<DirLock, filename::Error> {
+        DirLock::new(dir, DirLockOptions { exclusive: true, ..DirLockOptions::default() })
+    }
+
+    /// Take a shared lock on `dir` using the default sentinel file name, waiting for any
+    /// existing exclusive holder to release it.
+    pub fn shared(dir: PathBuf) -> Result<DirLock, filename::Error> {
+        DirLock::new(dir, DirLockOptions { exclusive: false, ..DirLockOptions::default() })
+    }
+
+    /// The directory this lock guards.
+    pub fn path(&self) -> &Path {
+        self.inner.path()
+    }
+
+    /// The underlying sentinel-file lock, should finer-grained access (e.g. byte-range locking)
+    /// be needed.
+    pub fn file_lock(&mut self) -> &mut FileLock {
+        self.inner.file_lock()
+    }
+}
+
+/// `dirlock::DirLock::new` only ever takes a `LockKind::Blocking`/`NonBlocking` lock, never a
+/// timeout-bounded one, so the `lock::Error::TimedOut` arm below is unreachable in practice -
+/// see the equivalent note on `RwLock::try_acquire` in `rwlock.rs`.
+fn to_filename_error(path: PathBuf, err: flock::Error) -> filename::Error {
+    match err {
+        flock::Error::IoError(path, io_err) => filename::Error::IoError(path, io_err),
+        flock::Error::LockError(lock::Error::Errno(errno)) =>
+            filename::Error::LockError(path, fd::Error::Errno(errno)),
+        flock::Error::LockError(lock::Error::TimedOut) =>
+            unreachable!("dirlock::DirLock::new does not time out"),
+    }
+}
@@ -0,0 +1,90 @@
+use std::path::{Path, PathBuf};
+use std::fs::{create_dir_all, OpenOptions};
+
+use flock::{self, FileLock};
+use lock::{LockKind, AccessMode};
+
+/// Options controlling how [`DirLock`](struct.DirLock.html) locks a
+/// directory.
+#[derive(Debug, Clone)]
+pub struct DirLockOptions {
+    /// Take an exclusive (single-writer) lock rather than a shared
+    /// (multi-reader) one.
+    pub exclusive: bool,
+    /// Fail immediately instead of waiting if the lock is already held.
+    pub non_blocking: bool,
+    /// The name of the sentinel lock file created inside the locked
+    /// directory.
+    pub lock_file_name: String,
+}
+
+impl Default for DirLockOptions {
+    fn default() -> DirLockOptions {
+        DirLockOptions {
+            exclusive: true,
+            non_blocking: false,
+            lock_file_name: ".lock".to_string(),
+        }
+    }
+}
+
+/// A lock on an entire directory, rather than a single file.
+///
+/// Serializes access to `dir` by taking an fcntl lock on a sentinel file
+/// (`.lock` by default) created inside it, so any number of cooperating
+/// processes can guard a data store or cache directory the same way they'd
+/// guard a single file with [`FileLock`](struct.FileLock.html). The lock is
+/// released when the `DirLock` is dropped.
+#[derive(Debug)]
+pub struct DirLock {
+    dir: PathBuf,
+    lock: FileLock,
+}
+
+impl DirLock {
+    /// Locks `dir` according to `options`, creating `dir` (and any missing
+    /// parents) if it doesn't already exist.
+    pub fn new(dir: PathBuf, options: DirLockOptions) -> Result<DirLock, flock::Error> {
+        create_dir_all(&dir).map_err(|io_err| flock::Error::IoError(dir.clone(), io_err))?;
+
+        let mode = if options.exclusive { AccessMode::Write } else { AccessMode::Read };
+        let kind = if options.non_blocking { LockKind::NonBlocking } else { LockKind::Blocking };
+
+        let lock_path = dir.join(&options.lock_file_name);
+
+        // `FileLock` only creates the file it locks when `mode` is `Write` - fine for
+        // `exclusive()`, but `shared()` would otherwise fail with `NotFound` the first time a
+        // directory is locked before any writer has done so. The sentinel only needs to exist,
+        // not be owned by either lock, so create it here regardless of `mode`.
+        OpenOptions::new().create(true).truncate(false).write(true).open(&lock_path)
+                          .map_err(|io_err| flock::Error::IoError(lock_path.clone(), io_err))?;
+
+        let mut lock = FileLock::new(lock_path, mode);
+        lock.any_lock(kind)?;
+
+        Ok(DirLock { dir, lock })
+    }
+
+    /// Take an exclusive lock on `dir` using the default sentinel file name,
+    /// waiting for any existing holder to release it.
+    pub fn exclusive(dir: PathBuf) -> Result<DirLock, flock::Error> {
+        DirLock::new(dir, DirLockOptions { exclusive: true, ..DirLockOptions::default() })
+    }
+
+    /// Take a shared lock on `dir` using the default sentinel file name,
+    /// waiting for any existing exclusive holder to release it.
+    pub fn shared(dir: PathBuf) -> Result<DirLock, flock::Error> {
+        DirLock::new(dir, DirLockOptions { exclusive: false, ..DirLockOptions::default() })
+    }
+
+    /// The directory this lock guards.
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The underlying sentinel-file lock, should finer-grained access
+    /// (e.g. byte-range locking) be needed.
+    pub fn file_lock(&mut self) -> &mut FileLock {
+        &mut self.lock
+    }
+}
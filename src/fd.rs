@@ -1,25 +1,83 @@
+#[cfg(unix)]
 use std::os::unix::io::RawFd;
+#[cfg(windows)]
+use std::os::windows::io::RawHandle;
 use std::fmt;
 use std::error::Error as ErrorTrait;
 use errno;
-use libc::c_int;
+#[cfg(unix)]
+use libc::{c_int, pid_t};
 pub use util::{Kind, Mode, ParseError};
 
-extern {
+/// The kind of descriptor `Lock` locks on this platform: a `RawFd` on Unix,
+/// a `RawHandle` on Windows.
+#[cfg(unix)]
+pub type Descriptor = RawFd;
+#[cfg(windows)]
+pub type Descriptor = RawHandle;
+
+#[cfg(unix)]
+extern "C" {
     fn c_lock(fd: i32, should_block: i32, is_write_lock: i32) -> c_int;
     fn c_unlock(fd: i32) -> c_int;
+    fn c_lock_range(fd: i32, should_block: i32, is_write_lock: i32, start: i64, len: i64) -> c_int;
+    fn c_unlock_range(fd: i32, start: i64, len: i64) -> c_int;
+    fn c_test_region(fd: i32, is_write_lock: i32, start: i64, len: i64,
+                      out_conflict: *mut i32, out_pid: *mut pid_t, out_is_write_lock: *mut i32) -> c_int;
 }
 
+/// A byte range within a file, as passed to `fcntl`'s `struct flock`.
+///
+/// `len == 0` means "to the end of the file", per POSIX, so a region
+/// locked this way keeps covering appended data as the file grows.
+///
+/// Byte-range locking and conflict testing are only implemented on Unix;
+/// Windows only gets the whole-file `lock`/`unlock` below.
+#[cfg(unix)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LockRegion {
+    pub start: i64,
+    pub len: i64,
+}
+
+#[cfg(unix)]
+impl LockRegion {
+    pub fn new(start: i64, len: i64) -> LockRegion {
+        LockRegion { start, len }
+    }
+
+    /// The region covering the whole file.
+    pub fn whole_file() -> LockRegion {
+        LockRegion { start: 0, len: 0 }
+    }
+}
+
+/// An existing lock that conflicts with a region passed to
+/// [`test_region`](fn.test_region.html).
+#[cfg(unix)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    /// The pid of the process holding the conflicting lock.
+    pub pid: pid_t,
+    /// The access mode of the conflicting lock.
+    pub mode: Mode,
+}
 
 /// Represents a write lock on a file.
 ///
 /// The `lock(Kind)` method tries to obtain a write-lock on the
-/// file identified by a file-descriptor. 
+/// file identified by a file-descriptor.
 /// One can obtain different kinds of write-locks.
 ///
 /// * Kind::NonBlocking - immediately return with an `Errno` error.
 /// * Kind::Blocking - waits (i.e. blocks the running thread) for the current
-/// owner of the lock to relinquish the lock.
+///   owner of the lock to relinquish the lock.
+///
+/// Note the POSIX gotcha that `fcntl` locks are associated with the
+/// *process*, and are dropped as soon as *any* file descriptor referring to
+/// the locked file is closed - not just the one the lock was taken through.
+/// Keep the fd that was locked alive (owned by this `Lock`) for as long as
+/// the lock should be held.
 ///
 /// # Example
 ///
@@ -35,7 +93,7 @@ extern {
 /// use std::os::unix::io::AsRawFd;
 ///
 /// fn main() {
-///     let f = tempfile::TempFile::new().unwrap();
+///     let f = tempfile::tempfile().unwrap();
 ///
 ///     match Lock::new(f.as_raw_fd()).lock(Kind::NonBlocking, Mode::Write) {
 ///         Ok(_)  => {
@@ -51,7 +109,7 @@ extern {
 /// ```
 #[derive(Debug, Eq, PartialEq)]
 pub struct Lock {
-    fd: RawFd,
+    fd: Descriptor,
 }
 
 
@@ -82,12 +140,13 @@ impl ErrorTrait for Error {
 }
 
 /// Obtain a write-lock the file-descriptor
-/// 
+///
 /// For an example, please see the documentation of the [`Lock`](struct.Lock.html) structure.
-pub fn lock(fd: RawFd, kind: Kind, mode: Mode) -> Result<(), Error> {
+#[cfg(unix)]
+pub fn lock(fd: Descriptor, kind: Kind, mode: Mode) -> Result<(), Error> {
     let errno = unsafe { c_lock(fd, kind.into(), mode.into()) };
 
-    return match errno {
+    match errno {
        0 => Ok(()),
        _ => Err(Error::Errno(errno::Errno(errno))),
     }
@@ -100,33 +159,157 @@ pub fn lock(fd: RawFd, kind: Kind, mode: Mode) -> Result<(), Error> {
 /// will be called automatically.
 ///
 /// For an example, please see the documentation of the [`Lock`](struct.Lock.html) structure.
-pub fn unlock(fd: RawFd) -> Result<(), Error> {
+#[cfg(unix)]
+pub fn unlock(fd: Descriptor) -> Result<(), Error> {
   unsafe {
     let errno = c_unlock(fd);
 
-    return match errno {
+    match errno {
        0 => Ok(()),
        _ => Err(Error::Errno(errno::Errno(errno))),
     }
   }
 }
 
+/// Like [`lock`](fn.lock.html), but only locks `region` rather than the
+/// whole file, so independent regions of the file can be locked
+/// concurrently by different processes.
+#[cfg(unix)]
+pub fn lock_range(fd: Descriptor, kind: Kind, mode: Mode, region: LockRegion) -> Result<(), Error> {
+    let errno = unsafe {
+        c_lock_range(fd, kind.into(), mode.into(), region.start, region.len)
+    };
+
+    match errno {
+        0 => Ok(()),
+        _ => Err(Error::Errno(errno::Errno(errno))),
+    }
+}
+
+/// Releases whatever lock [`lock_range`](fn.lock_range.html) took on
+/// `region`.
+#[cfg(unix)]
+pub fn unlock_range(fd: Descriptor, region: LockRegion) -> Result<(), Error> {
+    let errno = unsafe { c_unlock_range(fd, region.start, region.len) };
+
+    match errno {
+        0 => Ok(()),
+        _ => Err(Error::Errno(errno::Errno(errno))),
+    }
+}
+
+/// Tests whether `region` could be locked in `mode` without actually taking
+/// the lock (`fcntl`'s `F_GETLK`). Returns the conflicting lock, if any, or
+/// `None` if the region is currently free.
+#[cfg(unix)]
+pub fn test_region(fd: Descriptor, mode: Mode, region: LockRegion) -> Result<Option<Conflict>, Error> {
+    let mut conflict: i32 = 0;
+    let mut pid: pid_t = 0;
+    let mut is_write_lock: i32 = 0;
+
+    let errno = unsafe {
+        c_test_region(fd, mode.into(), region.start, region.len, &mut conflict, &mut pid, &mut is_write_lock)
+    };
+
+    if errno != 0 {
+        return Err(Error::Errno(errno::Errno(errno)));
+    }
+
+    if conflict == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(Conflict {
+        pid,
+        mode: if is_write_lock != 0 { Mode::Write } else { Mode::Read },
+    }))
+}
+
+/// Windows counterpart of [`lock`](fn.lock.html) above, backed by
+/// `LockFileEx` instead of `fcntl`: `Mode::Write` maps to
+/// `LOCKFILE_EXCLUSIVE_LOCK`, and `Kind::NonBlocking` to
+/// `LOCKFILE_FAIL_IMMEDIATELY`.
+#[cfg(windows)]
+pub fn lock(fd: Descriptor, kind: Kind, mode: Mode) -> Result<(), Error> {
+    use std::mem;
+    use winapi::shared::minwindef::DWORD;
+    use winapi::um::fileapi::LockFileEx;
+    use winapi::um::minwinbase::{LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY, OVERLAPPED};
+
+    let mut flags: DWORD = 0;
+    if mode == Mode::Write {
+        flags |= LOCKFILE_EXCLUSIVE_LOCK;
+    }
+    if kind == Kind::NonBlocking {
+        flags |= LOCKFILE_FAIL_IMMEDIATELY;
+    }
+
+    let mut overlapped: OVERLAPPED = unsafe { mem::zeroed() };
+
+    let ok = unsafe { LockFileEx(fd as *mut _, flags, 0, !0, !0, &mut overlapped) };
+
+    match ok {
+        0 => Err(Error::Errno(errno::errno())),
+        _ => Ok(()),
+    }
+}
+
+/// Windows counterpart of [`unlock`](fn.unlock.html) above, backed by
+/// `UnlockFileEx`.
+#[cfg(windows)]
+pub fn unlock(fd: Descriptor) -> Result<(), Error> {
+    use std::mem;
+    use winapi::um::fileapi::UnlockFileEx;
+    use winapi::um::minwinbase::OVERLAPPED;
+
+    let mut overlapped: OVERLAPPED = unsafe { mem::zeroed() };
+
+    let ok = unsafe { UnlockFileEx(fd as *mut _, 0, !0, !0, &mut overlapped) };
+
+    match ok {
+        0 => Err(Error::Errno(errno::errno())),
+        _ => Ok(()),
+    }
+}
+
 
 impl Lock {
-    /// Create a new lock instance from the given file descriptor `fd`.
-    /// 
+    /// Create a new lock instance from the given descriptor `fd`.
+    ///
     /// You will have to call `lock(...)` on it to acquire any lock.
-    pub fn new(fd: RawFd) -> Lock {
+    pub fn new(fd: Descriptor) -> Lock {
         Lock {
-            fd:   fd,
+            fd,
         }
     }
 
     /// Obtain a write-lock the file-descriptor
-    /// 
+    ///
     /// For an example, please see the documentation of the [`Lock`](struct.Lock.html) structure.
     pub fn lock(&self, kind: Kind, mode: Mode) -> Result<(), Error> {
-        lock(self.fd, kind.clone(), mode.clone())
+        lock(self.fd, kind, mode)
+    }
+
+    /// Like [`lock`](#method.lock), but only locks `region` rather than the
+    /// whole file. Unix only, see [`LockRegion`](struct.LockRegion.html).
+    #[cfg(unix)]
+    pub fn lock_range(&self, kind: Kind, mode: Mode, region: LockRegion) -> Result<(), Error> {
+        lock_range(self.fd, kind, mode, region)
+    }
+
+    /// Releases whatever lock [`lock_range`](#method.lock_range) took on
+    /// `region`. Unix only.
+    #[cfg(unix)]
+    pub fn unlock_range(&self, region: LockRegion) -> Result<(), Error> {
+        unlock_range(self.fd, region)
+    }
+
+    /// Tests whether `region` could be locked in `mode` without taking the
+    /// lock. See the free function [`test_region`](fn.test_region.html).
+    /// Unix only.
+    #[cfg(unix)]
+    pub fn test_region(&self, mode: Mode, region: LockRegion) -> Result<Option<Conflict>, Error> {
+        test_region(self.fd, mode, region)
     }
 
     /// Unlocks the file held by `Lock`.
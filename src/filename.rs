@@ -1,14 +1,31 @@
+extern crate once_cell;
+
 use std::path::PathBuf;
 use std::fs::File;
 use std::io;
+use std::io::{Read, Write, Seek, SeekFrom};
 use std::fs::OpenOptions;
-use std::os::unix::io::{RawFd, AsRawFd};
+use std::os::unix::io::{RawFd, AsRawFd, AsFd, BorrowedFd};
 use std::fmt;
 use std::error::Error as ErrorTrait;
+use std::future::Future;
+use std::mem;
+use std::panic;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::Duration;
+
+use self::once_cell::sync::OnceCell;
 
 use fd;
 pub use util::{Mode, Kind, ParseError};
 
+/// The result of a fallible lock operation, e.g.
+/// [`lock_guarded`](struct.Lock.html#method.lock_guarded).
+pub type LockResult<T> = Result<T, Error>;
+
 #[derive(Debug)]
 pub enum Error {
     LockError(PathBuf, fd::Error),
@@ -39,10 +56,33 @@ impl ErrorTrait for Error {
 
 unsafe impl Send for Error {}
 
+/// Converts to the `io::Error` this crate's async API surfaces through `Future::Output`.
+/// `errno::Errno`'s `EAGAIN`/`EWOULDBLOCK` map onto `io::ErrorKind::WouldBlock`, same as
+/// `io::Error::from_raw_os_error` classifies them elsewhere in this crate (see `rwlock.rs`).
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        match err {
+            Error::IoError(_, io_err) => io_err,
+            Error::LockError(_, fd::Error::Errno(errno)) => io::Error::from_raw_os_error(errno.0),
+        }
+    }
+}
+
+/// Tracks whether a [`Lock`](struct.Lock.html) currently holds a lock, and if so, in which
+/// mode - so [`upgrade`](struct.Lock.html#method.upgrade)/[`downgrade`](struct.Lock.html#method.downgrade)
+/// can tell a held shared lock apart from a held exclusive one (or no lock at all) without
+/// re-querying the kernel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Unlocked,
+    Shared,
+    Exclusive,
+}
+
 /// A type creating a lock file on demand.
 ///
-/// It supports multiple reader, single writer semantics and encodes 
-/// whether read or write access is required in an interface similar 
+/// It supports multiple reader, single writer semantics and encodes
+/// whether read or write access is required in an interface similar
 /// to the one of the [`RwLock`](http://doc.rust-lang.org/std/sync/struct.RwLock.html)
 ///
 /// It will remove the lock file it possibly created in case a lock could be obtained.
@@ -50,48 +90,131 @@ unsafe impl Send for Error {}
 pub struct Lock {
     path: PathBuf,
     file: Option<File>,
-    mode: Mode
+    mode: Mode,
+    state: State,
 }
 
 impl Lock {
     pub fn new(path: PathBuf, mode: Mode) -> Lock {
         Lock {
-            path: path,
+            path,
             file: None,
-            mode: mode,
+            mode,
+            state: State::Unlocked,
+        }
+    }
+
+    /// Wraps an already-open `file`, locking that handle directly instead of opening `path`
+    /// itself - so callers can combine locking with their own `OpenOptions` setup (append mode,
+    /// custom permissions, ...) or reuse a handle obtained from elsewhere.
+    ///
+    /// Since no path was given, the path recorded for error messages and returned by
+    /// [`path`](#method.path) is empty.
+    pub fn from_file(file: File, mode: Mode) -> Lock {
+        Lock {
+            path: PathBuf::new(),
+            file: Some(file),
+            mode,
+            state: State::Unlocked,
         }
     }
 
+    // Tries a read-write open even for `Mode::Read`, so that a shared lock taken through this
+    // fd can later be converted to an exclusive one via `upgrade` - `fcntl` refuses to place a
+    // write lock on a descriptor that isn't open for writing, and reopening a fresh fd wouldn't
+    // help, since closing *any* fd on a file releases all of this process's locks on it.
+    //
+    // If that fails for lack of write permission, falls back to the access `mode` actually
+    // calls for - a `Mode::Read` lock shouldn't require write permission on the file to
+    // succeed; it just won't be able to `upgrade` later.
     fn opened_file_fd(&mut self) -> Result<RawFd, io::Error> {
         if let Some(ref file) = self.file {
             return Ok(file.as_raw_fd())
         }
 
-        let (raw_fd, file) = match OpenOptions::new()
-                                   .create(true)
-                                   .read(self.mode == Mode::Read)
-                                   .write(self.mode == Mode::Write)
-                                   .open(&self.path) {
+        let file = match OpenOptions::new()
+                                     .create(self.mode == Mode::Write)
+                                     .read(true)
+                                     .write(true)
+                                     .open(&self.path) {
+            Ok(file) => file,
+            Err(ref io_err) if self.mode == Mode::Read && io_err.kind() == io::ErrorKind::PermissionDenied =>
+                OpenOptions::new().read(true).open(&self.path)?,
             Err(io_err) => return Err(io_err),
-            Ok(file) => (file.as_raw_fd(), Some(file))
         };
 
-        self.file = file;
+        let raw_fd = file.as_raw_fd();
+        self.file = Some(file);
         Ok(raw_fd)
     }
 
-    pub fn any_lock(&mut self, kind: Kind) -> Result<(), Error> {
+    fn raw_lock(&mut self, kind: Kind, mode: Mode) -> Result<(), Error> {
         let fd = match self.opened_file_fd() {
             Ok(fd) => fd,
             Err(io_err) => return Err(Error::IoError(self.path.clone(), io_err))
         };
 
-        match fd::lock(fd, kind, self.mode.clone()) {
-            Ok(res) => Ok(res),
+        match fd::lock(fd, kind, mode) {
+            Ok(()) => Ok(()),
             Err(lock_err) => Err(Error::LockError(self.path.clone(), lock_err)),
         }
     }
 
+    pub fn any_lock(&mut self, kind: Kind) -> Result<(), Error> {
+        self.raw_lock(kind, self.mode)?;
+
+        self.state = match self.mode {
+            Mode::Read => State::Shared,
+            Mode::Write => State::Exclusive,
+        };
+        Ok(())
+    }
+
+    /// Converts a held shared lock into an exclusive one, without releasing it (and thus
+    /// without opening a window for another process to slip in and take the lock first).
+    ///
+    /// On Unix this re-issues the `fcntl` lock on the same fd with `Mode::Write`, which the
+    /// kernel applies as an atomic conversion. `kind` behaves as it does for
+    /// [`any_lock`](#method.any_lock): `Kind::Blocking` waits for conflicting readers to go
+    /// away, `Kind::NonBlocking` fails immediately instead. The tracked state is only updated
+    /// once that syscall succeeds, so a failed upgrade leaves the original shared lock intact.
+    ///
+    /// Fails if `self` isn't currently holding a shared lock.
+    pub fn upgrade(&mut self, kind: Kind) -> Result<(), Error> {
+        if self.state != State::Shared {
+            return Err(Error::IoError(self.path.clone(),
+                                       io::Error::new(io::ErrorKind::InvalidInput,
+                                       "upgrade() called without first taking a shared lock")));
+        }
+
+        self.raw_lock(kind, Mode::Write)?;
+
+        self.mode = Mode::Write;
+        self.state = State::Exclusive;
+        Ok(())
+    }
+
+    /// Converts a held exclusive lock back into a shared one, without releasing it in between.
+    ///
+    /// Relaxing an exclusive lock to a shared one never conflicts with another lock holder, so
+    /// unlike [`upgrade`](#method.upgrade) this always blocks for however long the underlying
+    /// `fcntl` call takes rather than accepting a `Kind`.
+    ///
+    /// Fails if `self` isn't currently holding an exclusive lock.
+    pub fn downgrade(&mut self) -> Result<(), Error> {
+        if self.state != State::Exclusive {
+            return Err(Error::IoError(self.path.clone(),
+                                       io::Error::new(io::ErrorKind::InvalidInput,
+                                       "downgrade() called without first taking an exclusive lock")));
+        }
+
+        self.raw_lock(Kind::Blocking, Mode::Read)?;
+
+        self.mode = Mode::Read;
+        self.state = State::Shared;
+        Ok(())
+    }
+
     pub fn lock(&mut self) -> Result<(), Error> {
         self.any_lock(Kind::Blocking)
     }
@@ -103,12 +226,15 @@ impl Lock {
     pub fn unlock(&mut self) -> Result<(), Error> {
         match self.file {
             Some(ref file) => match fd::unlock(file.as_raw_fd()) {
-                Ok(res) => Ok(res),
+                Ok(res) => {
+                    self.state = State::Unlocked;
+                    Ok(res)
+                },
                 Err(lock_err) => Err(Error::LockError(self.path.clone(), lock_err)),
             },
             None => Err(Error::IoError(self.path.clone(),
-                                       io::Error::new(io::ErrorKind::NotFound, 
-                                       "unlock() called before lock() or try_lock()").into()))
+                                       io::Error::new(io::ErrorKind::NotFound,
+                                       "unlock() called before lock() or try_lock()")))
         }
     }
 
@@ -119,10 +245,177 @@ impl Lock {
     pub fn file(&mut self) -> Option<&mut File> {
         self.file.as_mut()
     }
+
+    /// Takes the lock and, on success, wraps `self` in a [`LockGuard`](struct.LockGuard.html)
+    /// that holds it for as long as the guard is alive, releasing it on `Drop`.
+    ///
+    /// Unlike [`any_lock`](#method.any_lock)/[`file`](#method.file), which leave it up to the
+    /// caller to remember not to touch the file after unlocking, the guard only exposes the
+    /// file (via `Read`/`Write`/`Seek`, or `file`/`file_mut`) for as long as the lock is held.
+    pub fn lock_guarded(mut self, kind: Kind) -> LockResult<LockGuard> {
+        self.any_lock(kind)?;
+        Ok(LockGuard { lock: self })
+    }
+
+    /// Like [`lock_guarded`](#method.lock_guarded), but rather than blocking the calling
+    /// thread, returns a `Future` that only completes once `mode` has been granted.
+    ///
+    /// Each poll makes one `Kind::NonBlocking` attempt; on `WouldBlock` the future schedules a
+    /// wakeup a short while later (so it doesn't busy-poll) and returns `Poll::Pending` in the
+    /// meantime, letting an async executor run other work while the lock is contended. Only
+    /// `std::future`/`Context`/`Waker` are used, so this works under any executor.
+    pub fn lock_async(mut self, mode: Mode) -> LockFuture {
+        self.mode = mode;
+        LockFuture { lock: Some(self) }
+    }
 }
 
 impl Drop for Lock {
     fn drop(&mut self) {
         self.unlock().ok();
     }
-}
\ No newline at end of file
+}
+
+impl AsRawFd for Lock {
+    /// Panics if no file has been opened yet - i.e. neither
+    /// [`from_file`](#method.from_file) nor a locking method has been called.
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_ref().expect("Lock has no open file yet").as_raw_fd()
+    }
+}
+
+impl AsFd for Lock {
+    /// Panics if no file has been opened yet - i.e. neither
+    /// [`from_file`](#method.from_file) nor a locking method has been called.
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_ref().expect("Lock has no open file yet").as_fd()
+    }
+}
+
+/// An RAII guard holding a [`Lock`](struct.Lock.html), returned by
+/// [`Lock::lock_guarded`](struct.Lock.html#method.lock_guarded).
+///
+/// The lock is released when the guard is dropped. For as long as it is held, the guard gives
+/// access to the locked file through `file`/`file_mut`, or directly through `Read`, `Write` and
+/// `Seek`.
+#[derive(Debug)]
+pub struct LockGuard {
+    lock: Lock,
+}
+
+impl LockGuard {
+    /// The path of the locked file.
+    pub fn path(&self) -> &PathBuf {
+        self.lock.path()
+    }
+
+    /// The locked file.
+    pub fn file(&self) -> &File {
+        self.lock.file.as_ref().expect("LockGuard always holds an opened file")
+    }
+
+    /// The locked file, mutably.
+    pub fn file_mut(&mut self) -> &mut File {
+        self.lock.file.as_mut().expect("LockGuard always holds an opened file")
+    }
+}
+
+impl AsRawFd for LockGuard {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file().as_raw_fd()
+    }
+}
+
+impl AsFd for LockGuard {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file().as_fd()
+    }
+}
+
+impl Read for LockGuard {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file_mut().read(buf)
+    }
+}
+
+impl Write for LockGuard {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file_mut().flush()
+    }
+}
+
+impl Seek for LockGuard {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file_mut().seek(pos)
+    }
+}
+
+/// How long a pending [`LockFuture`](struct.LockFuture.html) waits before retrying a contended
+/// lock. Chosen to be short enough not to noticeably delay acquisition once the lock frees up,
+/// while not busy-polling the kernel.
+const LOCK_ASYNC_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+/// Wakers of every currently-pending `LockFuture`, drained and woken by the single ticker thread
+/// started by `ensure_ticker_running`. Sharing one thread/registry across every pending future -
+/// rather than a fresh `thread::spawn` per poll - keeps thread count bounded under contention.
+fn pending_wakers() -> &'static Mutex<Vec<Waker>> {
+    static REGISTRY: OnceCell<Mutex<Vec<Waker>>> = OnceCell::new();
+    REGISTRY.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Lazily starts the single background thread that wakes every pending `LockFuture` roughly
+/// every `LOCK_ASYNC_RETRY_DELAY`. A no-op after the first call.
+fn ensure_ticker_running() {
+    static STARTED: OnceCell<()> = OnceCell::new();
+    STARTED.get_or_init(|| {
+        thread::spawn(|| loop {
+            thread::sleep(LOCK_ASYNC_RETRY_DELAY);
+
+            let wakers = mem::take(&mut *pending_wakers().lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+            for waker in wakers {
+                // A foreign `Waker` impl panicking must not take the ticker thread down with
+                // it - every other pending `LockFuture` is depending on it to ever wake again.
+                let _ = panic::catch_unwind(panic::AssertUnwindSafe(|| waker.wake()));
+            }
+        });
+    });
+}
+
+/// A `Future` that resolves to a [`LockGuard`](struct.LockGuard.html) once the lock it was
+/// created for has been granted, returned by [`Lock::lock_async`](struct.Lock.html#method.lock_async).
+///
+/// Mirrors the reader/writer admission of a `std::sync::RwLock`/async-std's `RwLock` - any
+/// number of `Mode::Read` futures, or a single `Mode::Write` one, may hold the lock at once -
+/// but does so across processes via the same `fcntl`/`LockFileEx` calls as the rest of the
+/// crate, and without ever blocking the polling thread.
+pub struct LockFuture {
+    lock: Option<Lock>,
+}
+
+impl Future for LockFuture {
+    type Output = io::Result<LockGuard>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let mut lock = self.lock.take().expect("LockFuture polled after completion");
+
+        match lock.any_lock(Kind::NonBlocking) {
+            Ok(()) => Poll::Ready(Ok(LockGuard { lock })),
+            Err(err) => {
+                let io_err = io::Error::from(err);
+                if io_err.kind() != io::ErrorKind::WouldBlock {
+                    return Poll::Ready(Err(io_err));
+                }
+
+                ensure_ticker_running();
+                pending_wakers().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(cx.waker().clone());
+
+                self.lock = Some(lock);
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -1,12 +1,13 @@
 use std::path::PathBuf;
 use std::fs::File;
-use std::io;
-use std::fs::OpenOptions;
+use std::io::{self, Read, Write, Seek, SeekFrom};
+use std::fs::{OpenOptions, remove_file};
 use std::os::unix::io::{RawFd, AsRawFd};
 use std::fmt;
 use std::error::Error as ErrorTrait;
+use std::ops::{Deref, DerefMut};
 
-use lock::{self, LockKind, AccessMode, lock, unlock};
+use lock::{self, LockKind, AccessMode, lock, unlock, lock_range, unlock_range};
 
 #[derive(Debug)]
 pub enum Error {
@@ -44,10 +45,19 @@ impl From<lock::Error> for Error {
     }
 }
 
+/// How a `FileLock`'s lock is released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Release {
+    /// `unlock()` an fcntl advisory lock, leaving the file itself in place.
+    Unlock,
+    /// Delete the file - used when the lock *is* the file's existence.
+    DeleteFile,
+}
+
 /// A type creating a lock file on demand.
 ///
-/// It supports multiple reader, single writer semantics and encodes 
-/// whether read or write access is required in an interface similar 
+/// It supports multiple reader, single writer semantics and encodes
+/// whether read or write access is required in an interface similar
 /// to the one of the [`RwLock`](http://doc.rust-lang.org/std/sync/struct.RwLock.html)
 ///
 /// It will remove the lock file it possibly created in case a lock could be obtained.
@@ -55,25 +65,50 @@ impl From<lock::Error> for Error {
 pub struct FileLock {
     path: PathBuf,
     file: Option<File>,
-    mode: AccessMode
+    mode: AccessMode,
+    release: Release,
 }
 
 impl FileLock {
     pub fn new(path: PathBuf, mode: AccessMode) -> FileLock {
         FileLock {
-            path: path,
+            path,
             file: None,
-            mode: mode,
+            mode,
+            release: Release::Unlock,
         }
     }
 
+    /// Locks `path` by atomically *creating* it, rather than by taking an
+    /// fcntl advisory lock on an already-open descriptor: creation succeeds
+    /// iff no other holder exists (`io::ErrorKind::AlreadyExists` otherwise),
+    /// and the file is deleted - not just unlocked - on release.
+    ///
+    /// This matters because advisory fcntl locks are invisible to
+    /// non-cooperating tools, and vanish (without leaving a trace) if the
+    /// process holding them is SIGKILLed mid-operation; a lock file is
+    /// visible on disk either way.
+    pub fn new_exclusive_lockfile(path: PathBuf) -> Result<FileLock, Error> {
+        let file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(io_err) => return Err(Error::IoError(path, io_err)),
+        };
+
+        Ok(FileLock {
+            path,
+            file: Some(file),
+            mode: AccessMode::Write,
+            release: Release::DeleteFile,
+        })
+    }
+
     fn opened_file_fd(&mut self) -> Result<RawFd, io::Error> {
         if let Some(ref file) = self.file {
             return Ok(file.as_raw_fd())
         }
 
         let (raw_fd, file) = match OpenOptions::new()
-                                   .create(true)
+                                   .create(self.mode == AccessMode::Write)
                                    .read(self.mode == AccessMode::Read)
                                    .write(self.mode == AccessMode::Write)
                                    .open(&self.path) {
@@ -91,7 +126,8 @@ impl FileLock {
             Err(io_err) => return Err(Error::IoError(self.path.clone(), io_err))
         };
 
-        Ok(try!(lock(fd, kind, self.mode.clone())))
+        lock(fd, kind, self.mode.clone())?;
+        Ok(())
     }
 
     pub fn lock(&mut self) -> Result<(), Error> {
@@ -103,11 +139,58 @@ impl FileLock {
     }
 
     pub fn unlock(&mut self) -> Result<(), Error> {
+        if self.release == Release::DeleteFile {
+            self.file = None;
+            return remove_file(&self.path).map_err(|io_err| Error::IoError(self.path.clone(), io_err));
+        }
+
         match self.file {
-            Some(ref file) => Ok(try!(unlock(file.as_raw_fd()))),
+            Some(ref file) => {
+                unlock(file.as_raw_fd())?;
+                Ok(())
+            },
             None => Err(Error::IoError(self.path.clone(),
-                                       io::Error::new(io::ErrorKind::NotFound, 
-                                       "unlock() called before lock() or try_lock()").into()))
+                                       io::Error::new(io::ErrorKind::NotFound,
+                                       "unlock() called before lock() or try_lock()")))
+        }
+    }
+
+    /// Like [`any_lock`](#method.any_lock), but only locks the byte range
+    /// `[start, start + len)`, so independent regions of the file can be
+    /// locked concurrently by different processes. `len == 0` means "to the
+    /// end of the file", per POSIX.
+    pub fn any_lock_range(&mut self, kind: LockKind, start: i64, len: i64) -> Result<(), Error> {
+        let fd = match self.opened_file_fd() {
+            Ok(fd) => fd,
+            Err(io_err) => return Err(Error::IoError(self.path.clone(), io_err))
+        };
+
+        lock_range(fd, kind, self.mode.clone(), start, len)?;
+        Ok(())
+    }
+
+    /// Like [`lock`](#method.lock), but for the byte range `[start, start + len)`.
+    pub fn lock_range(&mut self, start: i64, len: i64) -> Result<(), Error> {
+        self.any_lock_range(LockKind::Blocking, start, len)
+    }
+
+    /// Like [`try_lock`](#method.try_lock), but for the byte range
+    /// `[start, start + len)`.
+    pub fn try_lock_range(&mut self, start: i64, len: i64) -> Result<(), Error> {
+        self.any_lock_range(LockKind::NonBlocking, start, len)
+    }
+
+    /// Releases whatever lock was taken by
+    /// [`any_lock_range`](#method.any_lock_range) on `[start, start + len)`.
+    pub fn unlock_range(&mut self, start: i64, len: i64) -> Result<(), Error> {
+        match self.file {
+            Some(ref file) => {
+                unlock_range(file.as_raw_fd(), start, len)?;
+                Ok(())
+            },
+            None => Err(Error::IoError(self.path.clone(),
+                                       io::Error::new(io::ErrorKind::NotFound,
+                                       "unlock_range() called before a lock_range() variant")))
         }
     }
 
@@ -118,10 +201,92 @@ impl FileLock {
     pub fn file(&mut self) -> Option<&mut File> {
         self.file.as_mut()
     }
+
+    /// Like [`any_lock`](#method.any_lock), but returns a
+    /// [`LockGuard`](struct.LockGuard.html) that releases the lock when dropped
+    /// instead of requiring a separate call to [`unlock`](#method.unlock).
+    pub fn any_lock_guard(&mut self, kind: LockKind) -> Result<LockGuard<'_>, Error> {
+        self.any_lock(kind)?;
+        Ok(LockGuard { lock: self })
+    }
+
+    /// Like [`lock`](#method.lock), but returns a [`LockGuard`](struct.LockGuard.html).
+    pub fn lock_guard(&mut self) -> Result<LockGuard<'_>, Error> {
+        self.any_lock_guard(LockKind::Blocking)
+    }
+
+    /// Like [`try_lock`](#method.try_lock), but returns a [`LockGuard`](struct.LockGuard.html).
+    pub fn try_lock_guard(&mut self) -> Result<LockGuard<'_>, Error> {
+        self.any_lock_guard(LockKind::NonBlocking)
+    }
 }
 
 impl Drop for FileLock {
     fn drop(&mut self) {
         self.unlock().ok();
     }
+}
+
+/// A scoped lock on a [`FileLock`](struct.FileLock.html)'s file.
+///
+/// Obtained from [`lock_guard`](struct.FileLock.html#method.lock_guard) or
+/// [`try_lock_guard`](struct.FileLock.html#method.try_lock_guard), it derefs to
+/// the underlying `File` and forwards `Read`, `Write` and `Seek` to it, so the
+/// common lock-then-do-I/O pattern doesn't need to reach through `file()`.
+/// Since a `FileLock`'s access mode is fixed at construction, one guard type
+/// covers both the read and the write case.
+///
+/// Releases the lock via `unlock()` when dropped.
+#[derive(Debug)]
+pub struct LockGuard<'a> {
+    lock: &'a mut FileLock,
+}
+
+impl<'a> LockGuard<'a> {
+    /// The path of the locked file.
+    pub fn path(&self) -> &PathBuf {
+        self.lock.path()
+    }
+}
+
+impl<'a> Deref for LockGuard<'a> {
+    type Target = File;
+
+    fn deref(&self) -> &File {
+        self.lock.file.as_ref().expect("file is open while locked")
+    }
+}
+
+impl<'a> DerefMut for LockGuard<'a> {
+    fn deref_mut(&mut self) -> &mut File {
+        self.lock.file.as_mut().expect("file is open while locked")
+    }
+}
+
+impl<'a> Read for LockGuard<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.deref_mut().read(buf)
+    }
+}
+
+impl<'a> Write for LockGuard<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.deref_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.deref_mut().flush()
+    }
+}
+
+impl<'a> Seek for LockGuard<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.deref_mut().seek(pos)
+    }
+}
+
+impl<'a> Drop for LockGuard<'a> {
+    fn drop(&mut self) {
+        self.lock.unlock().ok();
+    }
 }
\ No newline at end of file
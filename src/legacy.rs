@@ -0,0 +1,297 @@
+//! The original, single-file locking API this crate shipped before the
+//! [`lock`](../lock/index.html)/[`flock`](../flock/index.html) family took
+//! over as the primary API.
+//!
+//! Kept around for existing callers of [`FileLock::lock`](struct.FileLock.html#method.lock);
+//! new code should prefer [`file_lock::FileLock`](../struct.FileLock.html)
+//! (from [`flock`](../flock/index.html)), which supports shared vs exclusive
+//! locks, byte ranges and RAII guards that this type does not.
+
+use libc::c_int;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::{Error, Read, Write, Seek, SeekFrom};
+use std::os::unix::io::AsRawFd;
+
+extern "C" {
+    fn c_lock(fd: i32, is_blocking: i32, is_writeable: i32) -> c_int;
+    fn c_unlock(fd: i32) -> c_int;
+}
+
+/// Represents the actually locked file
+#[derive(Debug)]
+pub struct FileLock {
+    /// the `std::fs::File` of the file that's locked
+    pub file: File,
+}
+
+impl FileLock {
+    /// Try to lock the specified file
+    ///
+    /// # Parameters
+    ///
+    /// `filename` is the path of the file we want to lock on
+    ///
+    /// `is_blocking` is a flag to indicate if we should block if it's already locked
+    ///
+    /// `is_writable` is a flag to indicate if we want to lock for writing
+    ///
+    /// # Examples
+    ///
+    ///```
+    ///extern crate file_lock;
+    ///
+    ///use file_lock::legacy::FileLock;
+    ///use std::io::prelude::*;
+    ///
+    ///fn main() {
+    ///    let should_we_block  = true;
+    ///    let lock_for_writing = true;
+    ///
+    ///    let mut filelock = match FileLock::lock("myfile.txt", should_we_block, lock_for_writing) {
+    ///        Ok(lock) => lock,
+    ///        Err(err) => panic!("Error getting write lock: {}", err),
+    ///    };
+    ///
+    ///    filelock.file.write_all(b"Hello, World!").is_ok();
+    ///}
+    ///```
+    ///
+    pub fn lock(filename: &str, is_blocking: bool, is_writable: bool) -> Result<FileLock, Error> {
+        let file = OpenOptions::new()
+            .read(!is_writable)
+            .write(is_writable)
+            .create(is_writable)
+            .open(filename);
+
+        match file {
+            Err(err) => Err(err),
+            Ok(file) => {
+                let errno = unsafe {
+                    c_lock(file.as_raw_fd(), is_blocking as i32, is_writable as i32)
+                };
+
+                match errno {
+                    0 => Ok(FileLock { file }),
+                    _ => Err(Error::from_raw_os_error(errno)),
+                }
+            },
+        }
+    }
+
+    /// Unlock our locked file
+    ///
+    /// *Note:* This method is optional as the file lock will be unlocked automatically when dropped
+    ///
+    /// # Examples
+    ///
+    ///```
+    ///extern crate file_lock;
+    ///
+    ///use file_lock::legacy::FileLock;
+    ///use std::io::prelude::*;
+    ///
+    ///fn main() {
+    ///    let should_we_block  = true;
+    ///    let lock_for_writing = true;
+    ///
+    ///    let mut filelock = match FileLock::lock("myfile.txt", should_we_block, lock_for_writing) {
+    ///        Ok(lock) => lock,
+    ///        Err(err) => panic!("Error getting write lock: {}", err),
+    ///    };
+    ///
+    ///    filelock.file.write_all(b"Hello, World!").is_ok();
+    ///
+    ///    match filelock.unlock() {
+    ///        Ok(_)    => println!("Successfully unlocked the file"),
+    ///        Err(err) => panic!("Error unlocking the file: {}", err),
+    ///    };
+    ///}
+    ///```
+    ///
+    pub fn unlock(&self) -> Result<(), Error> {
+        let errno = unsafe {
+            c_unlock(self.file.as_raw_fd())
+        };
+
+        match errno {
+            0 => Ok(()),
+            _ => Err(Error::from_raw_os_error(errno)),
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.unlock();
+    }
+}
+
+impl Read for FileLock {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.file.read(buf)
+    }
+}
+
+impl Write for FileLock {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.file.flush()
+    }
+}
+
+impl Seek for FileLock {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error> {
+        self.file.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use nix::unistd::ForkResult::{Parent, Child};
+    use nix::unistd::fork;
+    use std::fs::remove_file;
+    use std::process;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    #[test]
+    fn read_write_seek_through_the_lock() {
+        let filename = "filelock-io.test";
+        let _ = remove_file(filename).is_ok();
+
+        {
+            let mut filelock = FileLock::lock(filename, true, true).unwrap();
+            filelock.write_all(b"Hello, World!").unwrap();
+        }
+
+        let mut filelock = FileLock::lock(filename, true, false).unwrap();
+        filelock.seek(SeekFrom::Start(0)).unwrap();
+
+        let mut contents = String::new();
+        filelock.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "Hello, World!");
+
+        let _ = remove_file(filename).is_ok();
+    }
+
+    #[test]
+    fn lock_and_unlock() {
+        let filename = "filelock.test";
+
+        for already_exists in &[true, false] {
+            for already_locked in &[true, false] {
+                for already_writable in &[true, false] {
+                    for is_blocking in &[true, false] {
+                        for is_writable in &[true, false] {
+                            if !*already_exists && (*already_locked || *already_writable) {
+                                // nonsensical tests
+                                continue;
+                            }
+
+                            let _ = remove_file(filename).is_ok();
+
+                            let parent_lock = match *already_exists {
+                                false => None,
+                                true  => {
+                                    let _ = OpenOptions::new()
+                                        .write(true)
+                                        .create(true)
+                                        .truncate(false)
+                                        .open(filename)
+                                        .is_ok();
+
+                                    match *already_locked {
+                                        false => None,
+                                        true  => match FileLock::lock(filename, true, *already_writable) {
+                                            Ok(lock) => Some(lock),
+                                            Err(err) => panic!("Error creating parent lock ({})", err),
+                                        },
+                                    }
+                                },
+                            };
+
+                            match unsafe { fork() } {
+                                Ok(Parent { child: _ }) => {
+                                    sleep(Duration::from_millis(150));
+
+                                    if let Some(lock) = parent_lock {
+                                        let _ = lock.unlock().is_ok();
+                                    }
+
+                                    sleep(Duration::from_millis(350));
+                                }
+                                Ok(Child) => {
+                                    let mut try_count = 0;
+                                    let mut locked    = false;
+
+                                    match *already_locked {
+                                        true => match *is_blocking {
+                                            true => {
+                                                match FileLock::lock(filename, *is_blocking, *is_writable) {
+                                                    Ok(_)  => { locked = true },
+                                                    Err(_) => panic!("Error getting lock after wating for release"),
+                                                }
+                                            },
+                                            false => {
+                                                for _ in 0..5 {
+                                                    match FileLock::lock(filename, *is_blocking, *is_writable) {
+                                                        Ok(_) => {
+                                                            locked = true;
+                                                            break;
+                                                        },
+                                                        Err(_) => {
+                                                            sleep(Duration::from_millis(50));
+                                                            try_count += 1;
+                                                        },
+                                                    }
+                                                }
+                                            },
+                                        },
+                                        false => match FileLock::lock(filename, *is_blocking, *is_writable) {
+                                            Ok(_)  => { locked = true },
+                                            Err(_) => match !*already_exists && !*is_writable {
+                                                true  => {},
+                                                false => panic!("Error getting lock with no competition"),
+                                            },
+                                        },
+                                    }
+
+                                    match !*already_exists && !is_writable {
+                                        true  => assert!(!locked, "Locking a non-existent file for reading should fail"),
+                                        false => assert!(locked, "Lock should have been successful"),
+                                    }
+
+                                    match *is_blocking {
+                                        true  => assert!(try_count == 0, "Try count should be zero when blocking"),
+                                        false => {
+                                            match *already_locked {
+                                                false => assert!(try_count == 0, "Try count should be zero when no competition"),
+                                                true  => match !*already_writable && !is_writable {
+                                                    true  => assert!(try_count == 0, "Read lock when locked for reading should succeed first go"),
+                                                    false => assert!(try_count >= 3, "Try count should be >= 3"),
+                                                },
+                                            }
+                                        },
+                                    }
+
+                                    process::exit(7);
+                                },
+                                Err(_) => {
+                                    panic!("Error forking tests :(");
+                                }
+                            }
+
+                            let _ = remove_file(filename).is_ok();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
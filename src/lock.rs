@@ -1,23 +1,31 @@
-use std::os::unix::io::RawFd;
 use std::str::FromStr;
 use std::fmt;
 use std::error::Error as ErrorTrait;
+use std::time::Duration;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, AsFd};
+#[cfg(windows)]
+use std::os::windows::io::{AsRawHandle, AsHandle};
+use errno;
 
-extern {
-    fn c_lock(fd: i32, should_block: i32, is_write_lock: i32) -> i32;
-    fn c_unlock(fd: i32) -> i32;
-}
-
+use sys;
+use sys::Descriptor;
 
-/// Represents a write lock on a file.
+/// Represents a reader/writer lock on a file.
 ///
-/// The `lock(LockKind)` method tries to obtain a write-lock on the
-/// file identified by a file-descriptor. 
-/// One can obtain different kinds of write-locks.
+/// The `lock(LockKind, AccessMode)` method tries to obtain a lock on the
+/// file identified by a file-descriptor, using `AccessMode` to pick between a
+/// shared lock (`AccessMode::Read`, any number of readers may hold it at
+/// once) and an exclusive lock (`AccessMode::Write`, only a single holder,
+/// excluding all readers and writers).
+/// One can obtain different kinds of locks.
 ///
 /// * LockKind::NonBlocking - immediately return with an `Errno` error.
 /// * LockKind::Blocking - waits (i.e. blocks the running thread) for the current
-/// owner of the lock to relinquish the lock.
+///   owner of the lock to relinquish the lock.
+///
+/// On Unix, `fd` is a `RawFd`; on Windows, it is a `RawHandle`. Either way,
+/// it must stay open for as long as the lock is held.
 ///
 /// # Example
 ///
@@ -33,23 +41,25 @@ extern {
 /// use std::os::unix::io::AsRawFd;
 ///
 /// fn main() {
-///     let f = tempfile::TempFile::new().unwrap();
+///     let f = tempfile::tempfile().unwrap();
 ///
 ///     match Lock::new(f.as_raw_fd()).lock(LockKind::NonBlocking, AccessMode::Write) {
 ///         Ok(_)  => {
 ///             // we have a lock, which is discarded automatically. Otherwise you could call
 ///             // `unlock()` to make it explicit
-///             // 
+///             //
 ///             println!("Got lock");
 ///         },
 ///         Err(Error::Errno(i))
 ///               => println!("Got filesystem error {}", i),
+///         Err(Error::TimedOut)
+///               => println!("Timed out waiting for lock"),
 ///     }
 /// }
 /// ```
 #[derive(Debug, Eq, PartialEq)]
 pub struct Lock {
-    fd: RawFd,
+    fd: Descriptor,
 }
 
 
@@ -58,14 +68,19 @@ pub struct Lock {
 pub enum Error {
     /// caused when the error occurred at the filesystem layer (see
     /// [errno](https://crates.io/crates/errno)).
-    Errno(i32),
+    Errno(errno::Errno),
+    /// returned by [`try_lock_for`](struct.Lock.html#method.try_lock_for)
+    /// when the lock could not be obtained within the requested timeout.
+    TimedOut,
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match *self {
             Error::Errno(ref errno)
-                => write!(f, "Lock operation failed: {}", errno)
+                => write!(f, "Lock operation failed: {}", errno),
+            Error::TimedOut
+                => write!(f, "Timed out waiting for the lock"),
         }
     }
 }
@@ -73,8 +88,10 @@ impl fmt::Display for Error {
 impl ErrorTrait for Error {
     fn description(&self) -> &str {
         match *self {
-            Error::Errno(_) 
+            Error::Errno(_)
                 => "Failed to acuire file lock",
+            Error::TimedOut
+                => "Timed out waiting for the lock",
         }
     }
 }
@@ -82,7 +99,7 @@ impl ErrorTrait for Error {
 /// Represents the kind of lock (e.g. *blocking*, *non-blocking*)
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum LockKind {
-    /// Indicates a lock file which 
+    /// Indicates a lock file which
     NonBlocking,
     Blocking,
 }
@@ -151,36 +168,41 @@ impl FromStr for AccessMode {
     }
 }
 
-impl Into<i32> for AccessMode {
-    fn into(self) -> i32 {
-        match self {
-            AccessMode::Read => 0,
-            AccessMode::Write => 1,
+impl LockKind {
+    fn should_block(&self) -> bool {
+        match *self {
+            LockKind::NonBlocking => false,
+            LockKind::Blocking => true,
         }
     }
 }
 
-impl Into<i32> for LockKind {
-    fn into(self) -> i32 {
-        match self {
-            LockKind::NonBlocking => 0,
-            LockKind::Blocking => 1,
+impl AccessMode {
+    fn is_write_lock(&self) -> bool {
+        match *self {
+            AccessMode::Read => false,
+            AccessMode::Write => true,
         }
     }
 }
 
-
-
-/// Obtain a write-lock the file-descriptor
-/// 
+/// Obtain a shared or exclusive lock on the file-descriptor, per `mode`
+///
 /// For an example, please see the documentation of the [`Lock`](struct.Lock.html) structure.
-pub fn lock(fd: RawFd, kind: LockKind, mode: AccessMode) -> Result<(), Error> {
-    let errno = unsafe { c_lock(fd, kind.into(), mode.into()) };
+pub fn lock(fd: Descriptor, kind: LockKind, mode: AccessMode) -> Result<(), Error> {
+    lock_range(fd, kind, mode, 0, 0)
+}
 
-    return match errno {
-       0 => Ok(()),
-       _ => Err(Error::Errno(errno)),
-    }
+/// Obtain a shared or exclusive lock on the byte range `[start, start + len)`
+/// of the file-descriptor, per `mode`.
+///
+/// `len == 0` means "to the end of the file", per POSIX, so that the locked
+/// range grows as the file is appended to. `lock()` is a convenience for
+/// locking the whole file, i.e. `lock_range(fd, kind, mode, 0, 0)`.
+pub fn lock_range(fd: Descriptor, kind: LockKind, mode: AccessMode, start: i64, len: i64)
+                                                            -> Result<(), Error> {
+    sys::lock_range(fd, kind.should_block(), mode.is_write_lock(), start, len)
+        .map_err(Error::Errno)
 }
 
 /// Unlocks the file held by `Lock`.
@@ -190,35 +212,75 @@ pub fn lock(fd: RawFd, kind: LockKind, mode: AccessMode) -> Result<(), Error> {
 /// will be called automatically.
 ///
 /// For an example, please see the documentation of the [`Lock`](struct.Lock.html) structure.
-pub fn unlock(fd: RawFd) -> Result<(), Error> {
-  unsafe {
-    let errno = c_unlock(fd);
+pub fn unlock(fd: Descriptor) -> Result<(), Error> {
+    unlock_range(fd, 0, 0)
+}
+
+/// Releases whatever lock was taken by [`lock_range`](fn.lock_range.html) on
+/// the byte range `[start, start + len)` of the file-descriptor.
+pub fn unlock_range(fd: Descriptor, start: i64, len: i64) -> Result<(), Error> {
+    sys::unlock_range(fd, start, len).map_err(Error::Errno)
+}
 
-    return match errno {
-       0 => Ok(()),
-       _ => Err(Error::Errno(errno)),
+/// Like [`lock`](fn.lock.html), but bounds the wait: instead of blocking
+/// forever, returns `Error::TimedOut` if the lock cannot be obtained within
+/// `timeout`.
+pub fn try_lock_for(fd: Descriptor, mode: AccessMode, timeout: Duration) -> Result<(), Error> {
+    let timeout_ms = timeout.as_secs() as i64 * 1000 + timeout.subsec_millis() as i64;
+
+    match sys::lock_range_timeout(fd, mode.is_write_lock(), 0, 0, timeout_ms) {
+        Ok(sys::TimeoutOutcome::Locked) => Ok(()),
+        Ok(sys::TimeoutOutcome::TimedOut) => Err(Error::TimedOut),
+        Err(errno) => Err(Error::Errno(errno)),
     }
-  }
 }
 
 
 impl Lock {
-    /// Create a new lock instance from the given file descriptor `fd`.
-    /// 
+    /// Create a new lock instance from the given descriptor `fd`.
+    ///
     /// You will have to call `lock(...)` on it to acquire any lock.
-    pub fn new(fd: RawFd) -> Lock {
+    pub fn new(fd: Descriptor) -> Lock {
         Lock {
-            fd:   fd,
+            fd,
         }
     }
 
-    /// Obtain a write-lock the file-descriptor
-    /// 
+    /// Create a new lock from anything that owns a descriptor (e.g. `File`),
+    /// instead of a bare `Descriptor` with no lifetime tie to its owner.
+    ///
+    /// Bounding on `AsFd` rather than `AsRawFd` means `owner` must actually
+    /// still have an open, valid descriptor at the point this is called -
+    /// `AsRawFd` gives no such guarantee, since a bare `RawFd` can outlive
+    /// the descriptor it once named (e.g. after the owner is closed or the
+    /// number is reused by an unrelated open elsewhere).
+    #[cfg(unix)]
+    pub fn from_fd<T: AsFd>(owner: &T) -> Lock {
+        Lock::new(owner.as_fd().as_raw_fd())
+    }
+
+    /// Windows counterpart of [`from_fd`](#method.from_fd), keyed off
+    /// `AsHandle` rather than `AsRawHandle`.
+    #[cfg(windows)]
+    pub fn from_fd<T: AsHandle>(owner: &T) -> Lock {
+        Lock::new(owner.as_handle().as_raw_handle())
+    }
+
+    /// Obtain a shared or exclusive lock on the file-descriptor, per `mode`
+    ///
     /// For an example, please see the documentation of the [`Lock`](struct.Lock.html) structure.
     pub fn lock(&self, kind: LockKind, mode: AccessMode) -> Result<(), Error> {
         lock(self.fd, kind.clone(), mode.clone())
     }
 
+    /// Obtain a shared or exclusive lock on the byte range `[start, start +
+    /// len)` of the file-descriptor, per `mode`. `len == 0` means "to the
+    /// end of the file".
+    pub fn lock_range(&self, kind: LockKind, mode: AccessMode, start: i64, len: i64)
+                                                            -> Result<(), Error> {
+        lock_range(self.fd, kind, mode, start, len)
+    }
+
     /// Unlocks the file held by `Lock`.
     ///
     /// In reality, you shouldn't need to call `unlock()`. As `Lock` implements
@@ -229,6 +291,19 @@ impl Lock {
     pub fn unlock(&self) -> Result<(), Error> {
         unlock(self.fd)
     }
+
+    /// Releases whatever lock was taken by [`lock_range`](#method.lock_range)
+    /// on the byte range `[start, start + len)`.
+    pub fn unlock_range(&self, start: i64, len: i64) -> Result<(), Error> {
+        unlock_range(self.fd, start, len)
+    }
+
+    /// Like [`lock`](#method.lock), but bounds the wait: instead of
+    /// blocking forever, returns `Error::TimedOut` if the lock cannot be
+    /// obtained within `timeout`.
+    pub fn try_lock_for(&self, mode: AccessMode, timeout: Duration) -> Result<(), Error> {
+        try_lock_for(self.fd, mode, timeout)
+    }
 }
 
 #[allow(unused_must_use)]
@@ -0,0 +1,52 @@
+use std::path::{Path, PathBuf};
+use std::fs::create_dir_all;
+
+use flock::{FileLock, Error};
+use lock::AccessMode;
+
+/// A directory that namespaces a set of named locks.
+///
+/// Modelled on cargo's `Filesystem`: rather than assembling
+/// `base.join(name)` paths by hand and handling `ENOENT` on the parent
+/// directory yourself, a `LockDir` creates its root (and any missing
+/// parents) up front and hands out a [`FileLock`](struct.FileLock.html)
+/// for each named entry inside it.
+#[derive(Debug)]
+pub struct LockDir {
+    root: PathBuf,
+}
+
+impl LockDir {
+    /// Wrap `root` as a `LockDir`, creating it - and any missing parent
+    /// directories - if it doesn't already exist.
+    pub fn open(root: PathBuf) -> Result<LockDir, Error> {
+        create_dir_all(&root).map_err(|io_err| Error::IoError(root.clone(), io_err))?;
+
+        Ok(LockDir { root })
+    }
+
+    /// The directory all locks handed out by this `LockDir` live under.
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Obtain a [`FileLock`](struct.FileLock.html) for the entry `name`
+    /// inside this directory, requesting `mode` access.
+    ///
+    /// The returned lock is not yet held - call
+    /// [`lock`](struct.FileLock.html#method.lock) or
+    /// [`try_lock`](struct.FileLock.html#method.try_lock) on it, as usual.
+    pub fn open_lock(&self, name: &str, mode: AccessMode) -> FileLock {
+        FileLock::new(self.root.join(name), mode)
+    }
+
+    /// Like [`open_lock`](#method.open_lock) with `AccessMode::Write`.
+    pub fn open_rw_lock(&self, name: &str) -> FileLock {
+        self.open_lock(name, AccessMode::Write)
+    }
+
+    /// Like [`open_lock`](#method.open_lock) with `AccessMode::Read`.
+    pub fn open_ro_lock(&self, name: &str) -> FileLock {
+        self.open_lock(name, AccessMode::Read)
+    }
+}
@@ -0,0 +1,181 @@
+use std::cell::UnsafeCell;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{RwLock as StdRwLock, RwLockReadGuard as StdReadGuard, RwLockWriteGuard as StdWriteGuard};
+
+use lock::{self, LockKind, AccessMode, Error};
+
+/// A reader/writer lock over any value that owns a file descriptor -
+/// a `File`, a socket, a pipe, anything implementing `AsRawFd` - rather
+/// than requiring a path string the way [`FileLock::lock`](struct.FileLock.html#method.lock)
+/// does. Shared readers or a single writer, same as
+/// [`std::sync::RwLock`](http://doc.rust-lang.org/std/sync/struct.RwLock.html),
+/// but enforced across processes via `fcntl` as well as in-process.
+///
+/// `fcntl` advisory locks are owned by the *process*, not the thread that
+/// took them - two threads of the same process calling `lock::lock` on the
+/// same fd would both succeed, since as far as `fcntl` is concerned the
+/// process already holds the lock. So `RwLock<T>` pairs the fcntl lock with
+/// an in-process `std::sync::RwLock<()>` taken first: the in-process lock
+/// is what actually keeps concurrent threads out of each other's way, and
+/// the fcntl lock extends that same read/write exclusion to other
+/// processes. That in-process synchronization is what makes `Sync` below
+/// sound - without it, two threads could both believe they held the
+/// exclusive lock.
+///
+/// The in-process `StdRwLock` also lets several threads hold concurrent
+/// `RwLockReadGuard`s over the same fd at once - but the fcntl lock
+/// underneath is a single per-fd, per-process lock with no notion of "how
+/// many readers currently want it held". Each acquire still (redundantly)
+/// re-takes the fcntl lock - `fcntl` is happy to re-lock an fd the process
+/// already holds - but `active_guards` tracks how many guards are alive so
+/// that only the one that brings the count back to 0 actually releases it,
+/// keeping the fd locked for as long as *any* guard (read or write) is
+/// still alive.
+pub struct RwLock<T: AsRawFd> {
+    inner: UnsafeCell<T>,
+    local: StdRwLock<()>,
+    active_guards: AtomicUsize,
+}
+
+unsafe impl<T: AsRawFd + Send> Send for RwLock<T> {}
+unsafe impl<T: AsRawFd + Send> Sync for RwLock<T> {}
+
+impl<T: AsRawFd> RwLock<T> {
+    pub fn new(inner: T) -> RwLock<T> {
+        RwLock { inner: UnsafeCell::new(inner), local: StdRwLock::new(()), active_guards: AtomicUsize::new(0) }
+    }
+
+    fn fd(&self) -> i32 {
+        unsafe { (*self.inner.get()).as_raw_fd() }
+    }
+
+    /// Blocks until a shared lock can be taken, then returns a guard
+    /// holding it.
+    pub fn read(&self) -> Result<RwLockReadGuard<'_, T>, Error> {
+        let local = self.local.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        lock::lock(self.fd(), LockKind::Blocking, AccessMode::Read)?;
+        self.active_guards.fetch_add(1, Ordering::SeqCst);
+        Ok(RwLockReadGuard { lock: self, _local: local })
+    }
+
+    /// Blocks until an exclusive lock can be taken, then returns a guard
+    /// holding it.
+    pub fn write(&self) -> Result<RwLockWriteGuard<'_, T>, Error> {
+        let local = self.local.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        lock::lock(self.fd(), LockKind::Blocking, AccessMode::Write)?;
+        self.active_guards.fetch_add(1, Ordering::SeqCst);
+        Ok(RwLockWriteGuard { lock: self, _local: local })
+    }
+
+    /// Like [`read`](#method.read), but fails with
+    /// `io::ErrorKind::WouldBlock` instead of blocking if the lock is
+    /// already held.
+    pub fn try_read(&self) -> io::Result<RwLockReadGuard<'_, T>> {
+        let local = match self.local.try_read() {
+            Ok(guard) => guard,
+            Err(_) => return Err(io::Error::from(io::ErrorKind::WouldBlock)),
+        };
+        self.try_acquire(AccessMode::Read)?;
+        self.active_guards.fetch_add(1, Ordering::SeqCst);
+        Ok(RwLockReadGuard { lock: self, _local: local })
+    }
+
+    /// Like [`write`](#method.write), but fails with
+    /// `io::ErrorKind::WouldBlock` instead of blocking if the lock is
+    /// already held.
+    pub fn try_write(&self) -> io::Result<RwLockWriteGuard<'_, T>> {
+        let local = match self.local.try_write() {
+            Ok(guard) => guard,
+            Err(_) => return Err(io::Error::from(io::ErrorKind::WouldBlock)),
+        };
+        self.try_acquire(AccessMode::Write)?;
+        self.active_guards.fetch_add(1, Ordering::SeqCst);
+        Ok(RwLockWriteGuard { lock: self, _local: local })
+    }
+
+    /// Releases the fcntl lock only once the last active guard (read or
+    /// write) has been dropped - see the rationale on `active_guards` above.
+    fn release_guard(&self) {
+        if self.active_guards.fetch_sub(1, Ordering::SeqCst) == 1 {
+            lock::unlock(self.fd()).ok();
+        }
+    }
+
+    fn try_acquire(&self, mode: AccessMode) -> io::Result<()> {
+        match lock::lock(self.fd(), LockKind::NonBlocking, mode) {
+            Ok(()) => Ok(()),
+            // `from_raw_os_error` classifies EAGAIN/EWOULDBLOCK as
+            // `io::ErrorKind::WouldBlock` on its own.
+            Err(Error::Errno(errno)) => Err(io::Error::from_raw_os_error(errno.0)),
+            // `lock::lock` never bounds its wait with a timeout.
+            Err(Error::TimedOut) => unreachable!("lock::lock does not time out"),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+/// A shared (read) lock on an `RwLock<T>`, obtained from
+/// [`read`](struct.RwLock.html#method.read) or
+/// [`try_read`](struct.RwLock.html#method.try_read).
+///
+/// Derefs to `T`. Releases the fcntl lock (and then the in-process lock)
+/// when dropped.
+pub struct RwLockReadGuard<'a, T: AsRawFd + 'a> {
+    lock: &'a RwLock<T>,
+    // Declaration order is drop order: the fcntl lock must be released
+    // before the in-process one, so another thread woken by the latter
+    // never observes the fd as still locked.
+    _local: StdReadGuard<'a, ()>,
+}
+
+impl<'a, T: AsRawFd> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.inner.get() }
+    }
+}
+
+impl<'a, T: AsRawFd> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.release_guard();
+    }
+}
+
+/// An exclusive (write) lock on an `RwLock<T>`, obtained from
+/// [`write`](struct.RwLock.html#method.write) or
+/// [`try_write`](struct.RwLock.html#method.try_write).
+///
+/// Derefs (mutably) to `T`. Releases the fcntl lock (and then the
+/// in-process lock) when dropped.
+pub struct RwLockWriteGuard<'a, T: AsRawFd + 'a> {
+    lock: &'a RwLock<T>,
+    // See the field-order comment on `RwLockReadGuard`.
+    _local: StdWriteGuard<'a, ()>,
+}
+
+impl<'a, T: AsRawFd> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.inner.get() }
+    }
+}
+
+impl<'a, T: AsRawFd> DerefMut for RwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.inner.get() }
+    }
+}
+
+impl<'a, T: AsRawFd> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.release_guard();
+    }
+}
@@ -0,0 +1,15 @@
+//! Platform-specific locking primitives used by [`Lock`](../lock/struct.Lock.html).
+//!
+//! Each platform module exposes the same `lock_range`/`unlock_range` free
+//! functions operating on that platform's native file descriptor type, so
+//! `lock.rs` can stay platform-agnostic.
+
+#[cfg(unix)]
+mod unix;
+#[cfg(unix)]
+pub use self::unix::{lock_range, unlock_range, lock_range_timeout, TimeoutOutcome, Descriptor};
+
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+pub use self::windows::{lock_range, unlock_range, lock_range_timeout, TimeoutOutcome, Descriptor};
@@ -0,0 +1,64 @@
+use std::os::unix::io::RawFd;
+use errno;
+use libc;
+
+/// The kind of descriptor `Lock` locks on this platform.
+pub type Descriptor = RawFd;
+
+extern "C" {
+    fn c_lock_range(fd: i32, should_block: i32, is_write_lock: i32, start: i64, len: i64) -> i32;
+    fn c_unlock_range(fd: i32, start: i64, len: i64) -> i32;
+    fn c_lock_range_timeout(fd: i32, is_write_lock: i32, start: i64, len: i64, timeout_ms: i64) -> i32;
+}
+
+/// The outcome of [`lock_range_timeout`](fn.lock_range_timeout.html).
+pub enum TimeoutOutcome {
+    Locked,
+    TimedOut,
+}
+
+/// Locks the byte range `[start, start + len)` of `fd`. `len == 0` means "to
+/// the end of the file", per POSIX.
+pub fn lock_range(fd: Descriptor, should_block: bool, is_write_lock: bool, start: i64, len: i64)
+                                                            -> Result<(), errno::Errno> {
+    let errno = unsafe {
+        c_lock_range(fd, should_block as i32, is_write_lock as i32, start, len)
+    };
+
+    match errno {
+        0 => Ok(()),
+        _ => Err(errno::Errno(errno)),
+    }
+}
+
+/// Unlocks the byte range `[start, start + len)` of `fd`.
+pub fn unlock_range(fd: Descriptor, start: i64, len: i64) -> Result<(), errno::Errno> {
+    let errno = unsafe { c_unlock_range(fd, start, len) };
+
+    match errno {
+        0 => Ok(()),
+        _ => Err(errno::Errno(errno)),
+    }
+}
+
+/// Like [`lock_range`](fn.lock_range.html), but bounds a blocking wait with
+/// `timeout_ms` milliseconds via `SIGALRM`/`setitimer` rather than waiting
+/// forever. Only meaningful for a blocking wait; there's nothing to bound
+/// for a non-blocking one.
+///
+/// Concurrent calls (on any fd) don't serialize against each other here -
+/// `c_lock_range_timeout` refcounts its own process-wide `SIGALRM` handler
+/// installation internally, only guarding that bookkeeping rather than the
+/// blocking `fcntl` wait itself.
+pub fn lock_range_timeout(fd: Descriptor, is_write_lock: bool, start: i64, len: i64, timeout_ms: i64)
+                                                            -> Result<TimeoutOutcome, errno::Errno> {
+    let errno = unsafe {
+        c_lock_range_timeout(fd, is_write_lock as i32, start, len, timeout_ms)
+    };
+
+    match errno {
+        0 => Ok(TimeoutOutcome::Locked),
+        e if e == libc::ETIMEDOUT => Ok(TimeoutOutcome::TimedOut),
+        _ => Err(errno::Errno(errno)),
+    }
+}
@@ -0,0 +1,92 @@
+use std::os::windows::io::RawHandle;
+use std::mem;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use errno;
+use winapi::shared::minwindef::DWORD;
+use winapi::um::fileapi::{LockFileEx, UnlockFileEx};
+use winapi::um::minwinbase::{LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY, OVERLAPPED};
+
+/// The kind of descriptor `Lock` locks on this platform.
+pub type Descriptor = RawHandle;
+
+fn overlapped_at(start: i64) -> OVERLAPPED {
+    let mut overlapped: OVERLAPPED = unsafe { mem::zeroed() };
+    overlapped.Offset = (start as u64 & 0xffff_ffff) as DWORD;
+    overlapped.OffsetHigh = ((start as u64) >> 32) as DWORD;
+    overlapped
+}
+
+// `len == 0` means "to the end of the file" (per POSIX, mirrored here);
+// Windows has no such sentinel, so we approximate it with the largest range
+// LockFileEx/UnlockFileEx can express.
+fn range_len(len: i64) -> (DWORD, DWORD) {
+    if len == 0 {
+        (!0, !0)
+    } else {
+        ((len as u64 & 0xffff_ffff) as DWORD, ((len as u64) >> 32) as DWORD)
+    }
+}
+
+/// Locks the byte range `[start, start + len)` of `fd`. `len == 0` means "to
+/// the end of the file", per POSIX.
+pub fn lock_range(fd: Descriptor, should_block: bool, is_write_lock: bool, start: i64, len: i64)
+                                                            -> Result<(), errno::Errno> {
+    let mut flags: DWORD = 0;
+
+    if is_write_lock {
+        flags |= LOCKFILE_EXCLUSIVE_LOCK;
+    }
+    if !should_block {
+        flags |= LOCKFILE_FAIL_IMMEDIATELY;
+    }
+
+    let mut overlapped = overlapped_at(start);
+    let (len_low, len_high) = range_len(len);
+
+    let ok = unsafe {
+        LockFileEx(fd as *mut _, flags, 0, len_low, len_high, &mut overlapped)
+    };
+
+    match ok {
+        0 => Err(errno::errno()),
+        _ => Ok(()),
+    }
+}
+
+/// Unlocks the byte range `[start, start + len)` of `fd`.
+pub fn unlock_range(fd: Descriptor, start: i64, len: i64) -> Result<(), errno::Errno> {
+    let mut overlapped = overlapped_at(start);
+    let (len_low, len_high) = range_len(len);
+
+    let ok = unsafe {
+        UnlockFileEx(fd as *mut _, 0, len_low, len_high, &mut overlapped)
+    };
+
+    match ok {
+        0 => Err(errno::errno()),
+        _ => Ok(()),
+    }
+}
+
+/// The outcome of [`lock_range_timeout`](fn.lock_range_timeout.html).
+pub enum TimeoutOutcome {
+    Locked,
+    TimedOut,
+}
+
+/// Windows has no `SIGALRM`/`setitimer` equivalent to interrupt a blocking
+/// `LockFileEx` wait, so this polls with `LOCKFILE_FAIL_IMMEDIATELY`
+/// instead, sleeping briefly between attempts until `timeout_ms` elapses.
+pub fn lock_range_timeout(fd: Descriptor, is_write_lock: bool, start: i64, len: i64, timeout_ms: i64)
+                                                            -> Result<TimeoutOutcome, errno::Errno> {
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms as u64);
+
+    loop {
+        match lock_range(fd, false, is_write_lock, start, len) {
+            Ok(()) => return Ok(TimeoutOutcome::Locked),
+            Err(_) if Instant::now() < deadline => sleep(Duration::from_millis(20)),
+            Err(_) => return Ok(TimeoutOutcome::TimedOut),
+        }
+    }
+}
@@ -3,7 +3,7 @@ use std::fmt;
 use std::error::Error;
 
 /// Represents the kind of lock (e.g. *blocking*, *non-blocking*)
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Kind {
     /// Attempt a lock without blocking the call
     NonBlocking,
@@ -12,7 +12,7 @@ pub enum Kind {
 }
 
 /// Represents a file access mode, e.g. read or write
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Mode {
     /// Use this to obtain a shared lock, i.e. there may be any amount of readers
     /// at the same time.
@@ -63,18 +63,18 @@ impl FromStr for Mode {
     }
 }
 
-impl Into<i32> for Mode {
-    fn into(self) -> i32 {
-        match self {
+impl From<Mode> for i32 {
+    fn from(mode: Mode) -> i32 {
+        match mode {
             Mode::Read => 0,
             Mode::Write => 1,
         }
     }
 }
 
-impl Into<i32> for Kind {
-    fn into(self) -> i32 {
-        match self {
+impl From<Kind> for i32 {
+    fn from(kind: Kind) -> i32 {
+        match kind {
             Kind::NonBlocking => 0,
             Kind::Blocking => 1,
         }
@@ -0,0 +1,38 @@
+extern crate file_lock;
+
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use file_lock::{AccessMode, LockKind};
+use file_lock::coordinated;
+
+#[test]
+fn two_threads_locking_the_same_path_are_serialized() {
+    let mut path = env::temp_dir();
+    path.push("file-lock-coordinated-test");
+    let _ = ::std::fs::remove_file(&path);
+
+    let order = Arc::new(Mutex::new(Vec::new()));
+
+    let threads: Vec<_> = (0..4).map(|i| {
+        let path = path.clone();
+        let order = order.clone();
+
+        thread::spawn(move || {
+            let guard = coordinated::lock(path, AccessMode::Write, LockKind::Blocking).unwrap();
+            order.lock().unwrap().push(i);
+            // hold the lock briefly so a racing thread would observe overlap
+            thread::sleep(::std::time::Duration::from_millis(10));
+            drop(guard);
+        })
+    }).collect();
+
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    assert_eq!(order.lock().unwrap().len(), 4, "every thread should have gotten its turn");
+
+    let _ = ::std::fs::remove_file(&path);
+}
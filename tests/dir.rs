@@ -0,0 +1,61 @@
+extern crate file_lock;
+
+use std::env;
+use std::process::{Command, ExitStatus};
+
+use file_lock::dir::{DirLock, DirLockOptions};
+
+const ENV_LOCK_DIR: &str = "DIR_LOCK_TEST_DIR_PATH";
+
+/// `fcntl` advisory locks are owned by the *process*, not the individual
+/// handle that took them - a second `DirLock::new` in the same process that
+/// holds the first one would merely re-lock on its own behalf and succeed.
+/// So, like `dirlock.rs` et al., conflict is exercised from a separate
+/// child process.
+#[test]
+fn exclusive_dir_lock_conflicts_with_a_second_exclusive_lock() {
+    match env::var(ENV_LOCK_DIR) {
+        Ok(dir) => {
+            let options = DirLockOptions { non_blocking: true, ..DirLockOptions::default() };
+            assert!(DirLock::new(dir.into(), options).is_err(),
+                    "a second exclusive lock on the same directory should fail while held");
+        },
+        Err(_) => {
+            let mut dir = env::temp_dir();
+            dir.push("file-lock-dir-test");
+
+            let _ = ::std::fs::remove_dir_all(&dir);
+
+            let mut first = DirLock::exclusive(dir.clone()).expect("no other holder exists yet");
+            assert_eq!(first.path(), dir.as_path());
+            assert!(first.file_lock().path().starts_with(&dir));
+
+            let exec_self_status = || -> ExitStatus {
+                Command::new(env::current_exe().unwrap())
+                        .env(ENV_LOCK_DIR, &dir)
+                        .output().unwrap().status
+            };
+
+            assert!(exec_self_status().success(), "other process observes the lock as held");
+
+            drop(first);
+
+            let options = DirLockOptions { non_blocking: true, ..DirLockOptions::default() };
+            DirLock::new(dir.clone(), options).expect("the lock should be free once released");
+
+            let _ = ::std::fs::remove_dir_all(&dir);
+        }
+    }
+}
+
+#[test]
+fn shared_dir_lock_on_a_fresh_directory_succeeds() {
+    let mut dir = env::temp_dir();
+    dir.push("file-lock-dir-shared-test");
+
+    let _ = ::std::fs::remove_dir_all(&dir);
+
+    DirLock::shared(dir.clone()).expect("a shared lock shouldn't require a prior exclusive one");
+
+    let _ = ::std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,33 @@
+extern crate file_lock;
+
+use std::io;
+use std::env;
+
+use file_lock::FileLock;
+
+#[test]
+fn exclusive_lockfile_existence_is_the_lock() {
+    let mut path = env::temp_dir();
+    path.push("file-lock-exclusive-lockfile-test");
+
+    // make sure a previous failed run didn't leave the file behind
+    let _ = ::std::fs::remove_file(&path);
+
+    let first = FileLock::new_exclusive_lockfile(path.clone())
+                         .expect("no other holder exists yet");
+
+    match FileLock::new_exclusive_lockfile(path.clone()) {
+        Err(file_lock::flock::Error::IoError(_, ref io_err))
+            if io_err.kind() == io::ErrorKind::AlreadyExists => {},
+        other => panic!("expected AlreadyExists, got {:?}", other),
+    }
+
+    assert!(path.exists(), "the lock file should exist while held");
+
+    drop(first);
+
+    assert!(!path.exists(), "the lock file should be removed once released");
+
+    FileLock::new_exclusive_lockfile(path.clone())
+             .expect("the lock file was removed, so this should succeed");
+}
@@ -0,0 +1,86 @@
+extern crate file_lock;
+
+mod support;
+
+use std::env;
+use std::os::unix::io::AsRawFd;
+use std::fs::{remove_file, File, OpenOptions};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use file_lock::*;
+use support::TempFile;
+
+const ENV_LOCK_FILE: &str = "LOCK_TIMEOUT_NOSERIALIZE_TEST_LOCK_FILE_PATH";
+const ENV_READY_FILE: &str = "LOCK_TIMEOUT_NOSERIALIZE_TEST_READY_FILE_PATH";
+const HOLD_TIME_MS: u64 = 800;
+
+/// Regression test: `try_lock_for` used to serialize *all* concurrent calls
+/// process-wide behind a mutex held across the entire blocking `fcntl` wait,
+/// rather than just guarding the `SIGALRM` handler bookkeeping - so a
+/// `try_lock_for` on a completely unrelated, uncontended file would queue
+/// behind another call's long wait on a different file instead of returning
+/// immediately.
+#[test]
+fn try_lock_for_on_an_unrelated_file_does_not_wait_behind_a_contended_one() {
+    match env::var(ENV_LOCK_FILE) {
+        Ok(path) => {
+            let file = OpenOptions::new().write(true).open(&path).unwrap();
+            let held = Lock::new(file.as_raw_fd());
+            held.lock(LockKind::NonBlocking, AccessMode::Write).unwrap();
+
+            File::create(env::var(ENV_READY_FILE).unwrap()).unwrap();
+
+            sleep(Duration::from_millis(HOLD_TIME_MS));
+        },
+        Err(_) => {
+            let contended = TempFile::new("lock-timeout-noserialize-contended", AccessMode::Write);
+            let free = TempFile::new("lock-timeout-noserialize-free", AccessMode::Write);
+
+            let mut ready_path = env::temp_dir();
+            ready_path.push("file-lock-timeout-noserialize-test-ready");
+            let _ = remove_file(&ready_path);
+
+            let mut child = Command::new(env::current_exe().unwrap())
+                                     .env(ENV_LOCK_FILE, contended.path())
+                                     .env(ENV_READY_FILE, &ready_path)
+                                     .stdin(Stdio::null())
+                                     .stdout(Stdio::null())
+                                     .stderr(Stdio::null())
+                                     .spawn().unwrap();
+
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !ready_path.exists() {
+                assert!(Instant::now() < deadline, "other process never took the lock");
+                sleep(Duration::from_millis(10));
+            }
+
+            // A long blocking wait on `contended`, racing with the uncontended wait on `free`
+            // started just below - before the fix, this wait's mutex would make the other one
+            // queue up behind it instead of returning right away.
+            let contended_lock = Lock::new(contended.fd());
+            let waiter = thread::spawn(move || {
+                contended_lock.try_lock_for(AccessMode::Write, Duration::from_millis(HOLD_TIME_MS + 1000))
+            });
+
+            // Give the waiter a moment to actually be inside its blocking wait.
+            sleep(Duration::from_millis(100));
+
+            let free_lock = Lock::new(free.fd());
+            let started = Instant::now();
+            free_lock.try_lock_for(AccessMode::Write, Duration::from_millis(200))
+                     .expect("uncontended lock on an unrelated file should succeed");
+            let elapsed = started.elapsed();
+
+            assert!(elapsed < Duration::from_millis(HOLD_TIME_MS),
+                    "uncontended lock took {:?}, suggesting it queued behind the contended wait", elapsed);
+
+            waiter.join().unwrap().expect("contended lock should succeed once the other process releases it");
+
+            child.wait().unwrap();
+            let _ = remove_file(&ready_path);
+        }
+    }
+}
@@ -0,0 +1,25 @@
+extern crate file_lock;
+
+use std::env;
+
+use file_lock::LockDir;
+
+#[test]
+fn open_rw_lock_creates_missing_parent_dirs() {
+    let mut root = env::temp_dir();
+    root.push("file-lock-lockdir-test");
+    root.push("nested");
+
+    let _ = ::std::fs::remove_dir_all(root.parent().unwrap());
+
+    let dir = LockDir::open(root.clone()).expect("should create the missing directory tree");
+    assert_eq!(dir.path(), root.as_path());
+
+    let mut index_lock = dir.open_rw_lock("index");
+    index_lock.try_lock().expect("no other holder exists yet");
+
+    assert!(root.join("index").exists(), "locking an entry should create its file");
+
+    drop(index_lock);
+    let _ = ::std::fs::remove_dir_all(root.parent().unwrap());
+}
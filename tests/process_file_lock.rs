@@ -5,18 +5,19 @@ mod support;
 use std::path::Path;
 use std::env;
 use std::process::{Command, ExitStatus, Child, Stdio};
-use std::thread::sleep_ms;
+use std::thread::sleep;
+use std::time::Duration;
 
 use file_lock::*;
 use support::TempFile;
 
-const ENV_LOCK_FILE: &'static str = "FILE_LOCK_TEST_LOCK_FILE_PATH";
-const ENV_LOCK_KIND: &'static str = "FILE_LOCK_TEST_LOCK_KIND";
-const ENV_ACCESS_MODE: &'static str = "FILE_LOCK_TEST_ACCESS_MODE";
+const ENV_LOCK_FILE: &str = "FILE_LOCK_TEST_LOCK_FILE_PATH";
+const ENV_LOCK_KIND: &str = "FILE_LOCK_TEST_LOCK_KIND";
+const ENV_ACCESS_MODE: &str = "FILE_LOCK_TEST_ACCESS_MODE";
 
 /// This must be long enough for any testing machine to bring up a process and 
 /// execute main.
-const WAIT_TIME: u32 = 250;
+const WAIT_TIME: u64 = 250;
 
 fn configure_command(mut cmd: Command, path: &Path, kind: LockKind, mode: AccessMode)
                                                             -> Command {
@@ -81,7 +82,7 @@ fn inter_process_file_lock() {
                         assert!(!exec_self_status(t.path(), LockKind::NonBlocking, 
                                                   AccessMode::Write).success(),
                                 "can't get non-blocking write lock");
-                        sleep_ms(WAIT_TIME);
+                        sleep(Duration::from_millis(WAIT_TIME));
                         fl.unlock().unwrap();
                         assert!(child.wait().unwrap().success(),
                                 "child should get write lock after waiting");
@@ -93,7 +94,7 @@ fn inter_process_file_lock() {
                                                   AccessMode::Read).success(),
                                 "can't get non-blocking read lock");
 
-                        sleep_ms(WAIT_TIME);
+                        sleep(Duration::from_millis(WAIT_TIME));
                         fl.unlock().unwrap();
 
                         assert!(child.wait().unwrap().success(),
@@ -119,7 +120,7 @@ fn inter_process_file_lock() {
                                       .success(),
                     "Cannot obtain exclusie lock while there is a reader");
 
-            sleep_ms(WAIT_TIME);
+            sleep(Duration::from_millis(WAIT_TIME));
             fl.unlock().unwrap();
 
             assert!(child.wait().unwrap().success(),
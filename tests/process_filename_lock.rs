@@ -5,18 +5,19 @@ mod support;
 use std::path::Path;
 use std::env;
 use std::process::{Command, ExitStatus, Child, Stdio};
-use std::thread::sleep_ms;
+use std::thread::sleep;
+use std::time::Duration;
 
 use file_lock::filename::{Lock, Kind, Mode};
 use support::TempFile;
 
-const ENV_LOCK_FILE: &'static str = "FILE_LOCK_TEST_LOCK_FILE_PATH";
-const ENV_LOCK_KIND: &'static str = "FILE_LOCK_TEST_LOCK_KIND";
-const ENV_ACCESS_MODE: &'static str = "FILE_LOCK_TEST_ACCESS_MODE";
+const ENV_LOCK_FILE: &str = "FILE_LOCK_TEST_LOCK_FILE_PATH";
+const ENV_LOCK_KIND: &str = "FILE_LOCK_TEST_LOCK_KIND";
+const ENV_ACCESS_MODE: &str = "FILE_LOCK_TEST_ACCESS_MODE";
 
-/// This must be long enough for any testing machine to bring up a process and 
+/// This must be long enough for any testing machine to bring up a process and
 /// execute main.
-const WAIT_TIME: u32 = 250;
+const WAIT_TIME: u64 = 250;
 
 fn configure_command(mut cmd: Command, path: &Path, kind: Kind, mode: Mode)
                                                             -> Command {
@@ -60,40 +61,40 @@ fn inter_process_file_lock() {
 
                 match *kind {
                     Kind::NonBlocking => {
-                        assert!(!exec_self_status(t.path(), kind.clone(), Mode::Write)
+                        assert!(!exec_self_status(t.path(), *kind, Mode::Write)
                                                   .success()
                                 , "child can't get exclusive one");
-                        assert!(!exec_self_status(t.path(), kind.clone(), Mode::Read)
+                        assert!(!exec_self_status(t.path(), *kind, Mode::Read)
                                                   .success()
                                 , "child can't get read lock");
 
                         fl.unlock().unwrap();
-                        assert!(exec_self_status(t.path(), kind.clone(), Mode::Write)
+                        assert!(exec_self_status(t.path(), *kind, Mode::Write)
                                                   .success()
                                 , "child can get exclusive lock");
-                        assert!(exec_self_status(t.path(), kind.clone(), Mode::Read)
+                        assert!(exec_self_status(t.path(), *kind, Mode::Read)
                                                   .success()
                                 , "child can get shared lock");
                     },
                     Kind::Blocking => {
-                        let mut child = exec_self_child(t.path(), kind.clone(), 
+                        let mut child = exec_self_child(t.path(), *kind, 
                                                         Mode::Write);
                         assert!(!exec_self_status(t.path(), Kind::NonBlocking, 
                                                   Mode::Write).success(),
                                 "can't get non-blocking write lock");
-                        sleep_ms(WAIT_TIME);
+                        sleep(Duration::from_millis(WAIT_TIME));
                         fl.unlock().unwrap();
                         assert!(child.wait().unwrap().success(),
                                 "child should get write lock after waiting");
 
                         fl.lock().unwrap();
-                        let mut child = exec_self_child(t.path(), kind.clone(), 
+                        let mut child = exec_self_child(t.path(), *kind, 
                                                         Mode::Read);
                         assert!(!exec_self_status(t.path(), Kind::NonBlocking, 
                                                   Mode::Read).success(),
                                 "can't get non-blocking read lock");
 
-                        sleep_ms(WAIT_TIME);
+                        sleep(Duration::from_millis(WAIT_TIME));
                         fl.unlock().unwrap();
 
                         assert!(child.wait().unwrap().success(),
@@ -109,7 +110,7 @@ fn inter_process_file_lock() {
             fl.try_lock().unwrap();
 
             for kind in &[Kind::NonBlocking, Kind::Blocking] {
-                assert!(exec_self_status(t.path(), kind.clone(), Mode::Read)
+                assert!(exec_self_status(t.path(), *kind, Mode::Read)
                                         .success()
                         , "child can get shared lock");
             }
@@ -119,7 +120,7 @@ fn inter_process_file_lock() {
                                       .success(),
                     "Cannot obtain exclusie lock while there is a reader");
 
-            sleep_ms(WAIT_TIME);
+            sleep(Duration::from_millis(WAIT_TIME));
             fl.unlock().unwrap();
 
             assert!(child.wait().unwrap().success(),
@@ -0,0 +1,51 @@
+extern crate file_lock;
+
+mod support;
+
+use std::env;
+use std::process::{Command, ExitStatus};
+
+use file_lock::*;
+use support::TempFile;
+
+const ENV_LOCK_FILE: &str = "FILE_LOCK_TEST_RANGE_LOCK_FILE_PATH";
+const ENV_RANGE_START: &str = "FILE_LOCK_TEST_RANGE_START";
+const ENV_RANGE_LEN: &str = "FILE_LOCK_TEST_RANGE_LEN";
+
+fn exec_self_status(path: &str, start: i64, len: i64) -> ExitStatus {
+    Command::new(env::current_exe().unwrap())
+            .env(ENV_LOCK_FILE, path)
+            .env(ENV_RANGE_START, start.to_string())
+            .env(ENV_RANGE_LEN, len.to_string())
+            .output().unwrap().status
+}
+
+#[test]
+fn inter_process_range_lock() {
+    match env::var(ENV_LOCK_FILE) {
+        Ok(path) => {
+            let start: i64 = env::var(ENV_RANGE_START).unwrap().parse().unwrap();
+            let len: i64 = env::var(ENV_RANGE_LEN).unwrap().parse().unwrap();
+
+            FileLock::new(path.into(), AccessMode::Write)
+                     .any_lock_range(LockKind::NonBlocking, start, len).unwrap();
+        },
+        Err(_) => {
+            let t = TempFile::new("inter-process-range-lock-operation", AccessMode::Write);
+
+            let mut fl = FileLock::new(t.path_buf(), AccessMode::Write);
+            fl.try_lock_range(0, 10).unwrap();
+
+            assert!(exec_self_status(t.path().to_str().unwrap(), 10, 10).success(),
+                    "a non-overlapping range should lock fine while [0, 10) is held");
+
+            assert!(!exec_self_status(t.path().to_str().unwrap(), 5, 10).success(),
+                    "an overlapping range should conflict with the held [0, 10) lock");
+
+            fl.unlock_range(0, 10).unwrap();
+
+            assert!(exec_self_status(t.path().to_str().unwrap(), 5, 10).success(),
+                    "the range should be lockable again once released");
+        }
+    }
+}
@@ -11,7 +11,7 @@ use file_lock::*;
 use support::TempFile;
 
 
-const ENV_LOCK_FILE: &'static str = "LOCK_TEST_LOCK_FILE_PATH";
+const ENV_LOCK_FILE: &str = "LOCK_TEST_LOCK_FILE_PATH";
 
 #[test]
 fn inter_process_lock() {
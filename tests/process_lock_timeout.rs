@@ -0,0 +1,72 @@
+extern crate file_lock;
+
+mod support;
+
+use std::env;
+use std::os::unix::io::AsRawFd;
+use std::fs::{remove_file, File, OpenOptions};
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use file_lock::*;
+use support::TempFile;
+
+const ENV_LOCK_FILE: &str = "LOCK_TIMEOUT_TEST_LOCK_FILE_PATH";
+const ENV_READY_FILE: &str = "LOCK_TIMEOUT_TEST_READY_FILE_PATH";
+const HOLD_TIME_MS: u64 = 400;
+
+#[test]
+fn try_lock_for_times_out_while_another_process_holds_the_lock() {
+    match env::var(ENV_LOCK_FILE) {
+        Ok(path) => {
+            let file = OpenOptions::new().write(true).open(&path).unwrap();
+
+            // Bound to `held` so the lock outlives this statement - an
+            // unnamed temporary would be dropped (and so unlocked) as soon
+            // as `.lock()` returns, before the sleep below even starts.
+            let held = Lock::new(file.as_raw_fd());
+            held.lock(LockKind::NonBlocking, AccessMode::Write).unwrap();
+
+            // Signal the driver that the lock is held, only now that it
+            // actually is - a fixed sleep on the driver's side can't be
+            // sized reliably against this process's own startup time.
+            File::create(env::var(ENV_READY_FILE).unwrap()).unwrap();
+
+            sleep(Duration::from_millis(HOLD_TIME_MS));
+        },
+        Err(_) => {
+            let t = TempFile::new("inter-process-timed-lock-operation", AccessMode::Write);
+
+            let mut ready_path = env::temp_dir();
+            ready_path.push("file-lock-timeout-test-ready");
+            let _ = remove_file(&ready_path);
+
+            let mut child = Command::new(env::current_exe().unwrap())
+                                     .env(ENV_LOCK_FILE, t.path())
+                                     .env(ENV_READY_FILE, &ready_path)
+                                     .stdin(Stdio::null())
+                                     .stdout(Stdio::null())
+                                     .stderr(Stdio::null())
+                                     .spawn().unwrap();
+
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !ready_path.exists() {
+                assert!(Instant::now() < deadline, "other process never took the lock");
+                sleep(Duration::from_millis(10));
+            }
+
+            let l = Lock::new(t.fd());
+
+            assert_eq!(l.try_lock_for(AccessMode::Write, Duration::from_millis(100)),
+                       Err(Error::TimedOut),
+                       "the other process is still holding the lock");
+
+            assert!(l.try_lock_for(AccessMode::Write, Duration::from_millis(HOLD_TIME_MS + 500)).is_ok(),
+                    "the lock should be obtained once the other process releases it");
+
+            child.wait().unwrap();
+            let _ = remove_file(&ready_path);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+extern crate file_lock;
+
+mod support;
+
+use std::env;
+use std::fs::OpenOptions;
+use std::io::ErrorKind;
+use std::process::{Command, ExitStatus};
+
+use file_lock::RwLock;
+use support::Remover;
+
+const ENV_LOCK_FILE: &str = "RWLOCK_TEST_LOCK_FILE_PATH";
+
+/// `fcntl` advisory locks are owned by the *process*, so two `RwLock`s
+/// opened separately in the same process wouldn't conflict with each other
+/// even without the in-process synchronization this type adds - exercising
+/// a real conflict needs a second process, same as `process_lock.rs` et al.
+#[test]
+fn try_write_conflicts_with_a_held_write_lock() {
+    match env::var(ENV_LOCK_FILE) {
+        Ok(path) => {
+            let file = OpenOptions::new().write(true).open(&path).unwrap();
+            let rw = RwLock::new(file);
+            let result = rw.try_write().map(|_guard| ()).map_err(|err| err.kind());
+            assert_eq!(result, Err(ErrorKind::WouldBlock));
+        },
+        Err(_) => {
+            let mut path = env::temp_dir();
+            path.push("file-lock-rwlock-test");
+            let _remover = Remover { path: path.clone() };
+
+            let file = OpenOptions::new().create(true).truncate(false).write(true).open(&path).unwrap();
+            let rw = RwLock::new(file);
+            let _held = rw.write().unwrap();
+
+            let exec_self_status = || -> ExitStatus {
+                Command::new(env::current_exe().unwrap())
+                        .env(ENV_LOCK_FILE, &path)
+                        .output().unwrap().status
+            };
+
+            assert!(exec_self_status().success(), "other process observes the write lock as held");
+        }
+    }
+}
@@ -0,0 +1,70 @@
+extern crate file_lock;
+
+mod support;
+
+use std::env;
+use std::fs::OpenOptions;
+use std::io::ErrorKind;
+use std::process::{Command, ExitStatus};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use file_lock::RwLock;
+use support::Remover;
+
+const ENV_LOCK_FILE: &str = "RWLOCK_CONCURRENT_READERS_TEST_LOCK_FILE_PATH";
+
+/// Regression test: dropping one of two concurrent `RwLockReadGuard`s over
+/// the same `RwLock` used to release the underlying fcntl lock outright,
+/// exposing the file to an external writer while the other guard was still
+/// alive and believed to be protected.
+#[test]
+fn concurrent_readers_keep_the_fcntl_lock_held_until_the_last_one_drops() {
+    match env::var(ENV_LOCK_FILE) {
+        Ok(path) => {
+            let file = OpenOptions::new().write(true).open(&path).unwrap();
+            let rw = RwLock::new(file);
+            let result = rw.try_write().map(|_guard| ()).map_err(|err| err.kind());
+            assert_eq!(result, Err(ErrorKind::WouldBlock));
+        },
+        Err(_) => {
+            let mut path = env::temp_dir();
+            path.push("file-lock-rwlock-concurrent-readers-test");
+            let _remover = Remover { path: path.clone() };
+
+            let file = OpenOptions::new().create(true).truncate(false).read(true).write(true).open(&path).unwrap();
+            let rw = Arc::new(RwLock::new(file));
+
+            let exec_self_status = || -> ExitStatus {
+                Command::new(env::current_exe().unwrap())
+                        .env(ENV_LOCK_FILE, &path)
+                        .output().unwrap().status
+            };
+
+            let reader_a = rw.read().unwrap();
+
+            // Take a second, concurrent read guard on a separate thread, so both are alive at
+            // once - same as two unrelated readers of a shared `RwLock<T>` would be.
+            let (held_tx, held_rx) = mpsc::channel::<()>();
+            let (release_tx, release_rx) = mpsc::channel::<()>();
+            let rw_in_thread = Arc::clone(&rw);
+            let reader_b_thread = thread::spawn(move || {
+                let _reader_b = rw_in_thread.read().unwrap();
+                held_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+            });
+            held_rx.recv().unwrap();
+
+            drop(reader_a);
+
+            assert!(exec_self_status().success(),
+                    "the second reader is still alive - an external writer should still be locked out");
+
+            release_tx.send(()).unwrap();
+            reader_b_thread.join().unwrap();
+
+            assert!(!exec_self_status().success(),
+                    "both readers are gone now - an external writer should be able to take the lock");
+        }
+    }
+}
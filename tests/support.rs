@@ -9,6 +9,25 @@ use std::fs::{File, OpenOptions, remove_file};
 
 use file_lock::AccessMode;
 
+/// Bridges the two independent access-mode enums used across the test suite
+/// (`file_lock::AccessMode` and `file_lock::fd::Mode`) so `TempFile::new` can
+/// be shared by tests exercising either lock API.
+pub trait LockMode {
+    fn is_write(&self) -> bool;
+}
+
+impl LockMode for AccessMode {
+    fn is_write(&self) -> bool {
+        *self == AccessMode::Write
+    }
+}
+
+impl LockMode for file_lock::fd::Mode {
+    fn is_write(&self) -> bool {
+        *self == file_lock::fd::Mode::Write
+    }
+}
+
 /// A utility type to assure the removal of a file.
 ///
 /// It is useful when a temporary lock file is created. When an instance dropped
@@ -53,17 +72,25 @@ impl<P> TempFile<P> where P: Borrow<PathBuf> {
 }
 
 impl TempFile<PathBuf> {
-    pub fn new(name: &str, mode: AccessMode) -> TempFile<PathBuf> {
+    pub fn new<M: LockMode>(name: &str, mode: M) -> TempFile<PathBuf> {
         let mut path = env::temp_dir();
         path.push(name);
+        let is_write = mode.is_write();
+
+        // `create(true)` requires write access, so for a read-only temp
+        // file, make sure it exists first and then reopen it read-only.
+        if !is_write {
+            OpenOptions::new().write(true).create(true).truncate(false).open(&path).unwrap();
+        }
 
         TempFile {
             inner: OpenOptions::new()
-                               .create(true)
-                               .read(mode == AccessMode::Read)
-                               .write(mode == AccessMode::Write)
+                               .create(is_write)
+                               .truncate(false)
+                               .read(!is_write)
+                               .write(is_write)
                                .open(&path).unwrap(),
-            remover: Remover { path: path },
+            remover: Remover { path },
         }
     }
 }
\ No newline at end of file
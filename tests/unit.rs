@@ -9,7 +9,7 @@ use std::env;
 use std::fs;
 
 use support::{TempFile, Remover};
-use file_lock::fd::{Lock, Error, Kind, Mode};
+use file_lock::fd::{Lock, Error, Kind, Mode, LockRegion};
 use file_lock::filename::Lock as FileLock;
 
 //
@@ -23,12 +23,12 @@ use file_lock::filename::Lock as FileLock;
 fn invalid_fd() {
     for fd in &[-1 as RawFd, 40125] {
         for kind in &[Kind::Blocking, Kind::NonBlocking] {
-            assert_eq!(Lock::new(*fd).lock(kind.clone(), Mode::Write), 
-                       Err(Error::Errno(errno::Errno(libc::consts::os::posix88::EBADF))));
+            assert_eq!(Lock::new(*fd).lock(*kind, Mode::Write), 
+                       Err(Error::Errno(errno::Errno(libc::EBADF))));
         }
 
-        assert_eq!(Lock::new(*fd).unlock(), 
-                   Err(Error::Errno(errno::Errno(libc::consts::os::posix88::EBADF))));
+        assert_eq!(Lock::new(*fd).unlock(),
+                   Err(Error::Errno(errno::Errno(libc::EBADF))));
     }
 }
 
@@ -36,7 +36,7 @@ fn invalid_fd() {
 fn lock_ok() {
     let tmp = TempFile::new("file-lock-test", Mode::Write);
     for kind in &[Kind::Blocking, Kind::NonBlocking] {
-        assert_eq!(Lock::new(tmp.fd()).lock(kind.clone(), Mode::Write), Ok(()));
+        assert_eq!(Lock::new(tmp.fd()).lock(*kind, Mode::Write), Ok(()));
     }
 }
 
@@ -44,11 +44,11 @@ fn lock_ok() {
 fn unlock_error() {
     let tmp = TempFile::new("file-lock-test", Mode::Write);
     for kind in &[Kind::Blocking, Kind::NonBlocking] {
-        assert_eq!(Lock::new(tmp.fd()).lock(kind.clone(), Mode::Write), Ok(()));
+        assert_eq!(Lock::new(tmp.fd()).lock(*kind, Mode::Write), Ok(()));
 
         // fcntl() will only allow us to hold a single lock on a file at a time
         // so this test can't work :(
-        assert_eq!(Lock::new(tmp.fd()).lock(kind.clone(), Mode::Write), Ok(()));
+        assert_eq!(Lock::new(tmp.fd()).lock(*kind, Mode::Write), Ok(()));
 
 
         // unlock without prior lock 
@@ -62,12 +62,31 @@ fn unlock_ok() {
     for kind in &[Kind::Blocking, Kind::NonBlocking] {
         let l = Lock::new(tmp.fd());
 
-        assert_eq!(l.lock(kind.clone(), Mode::Write), Ok(()));
+        assert_eq!(l.lock(*kind, Mode::Write), Ok(()));
         assert_eq!(l.unlock(), Ok(()));
         assert!(l.unlock().is_ok(), "extra unlocks are fine");
     }
 }
 
+#[test]
+fn lock_range_non_overlapping_succeeds() {
+    let tmp = TempFile::new("file-lock-range-test", Mode::Write);
+    let l = Lock::new(tmp.fd());
+
+    assert_eq!(l.lock_range(Kind::NonBlocking, Mode::Write, LockRegion::new(0, 10)), Ok(()));
+    assert_eq!(l.lock_range(Kind::NonBlocking, Mode::Write, LockRegion::new(10, 10)), Ok(()));
+    assert_eq!(l.unlock_range(LockRegion::new(0, 10)), Ok(()));
+    assert_eq!(l.unlock_range(LockRegion::new(10, 10)), Ok(()));
+}
+
+#[test]
+fn test_region_reports_no_conflict_when_free() {
+    let tmp = TempFile::new("file-lock-test-region-test", Mode::Write);
+    let l = Lock::new(tmp.fd());
+
+    assert_eq!(l.test_region(Mode::Write, LockRegion::whole_file()), Ok(None));
+}
+
 #[test]
 fn file_lock_create_file() {
     use std::io::Write;
@@ -80,7 +99,7 @@ fn file_lock_create_file() {
         let r = Remover { path: fl.path().clone() };
         fl.lock().unwrap();
 
-        fl.file().unwrap().write(b"hello").unwrap();
+        fl.file().unwrap().write_all(b"hello").unwrap();
 
         assert!(fs::metadata(&path).is_ok(), "File should have been created");
         fl.unlock().unwrap();
@@ -90,3 +109,115 @@ fn file_lock_create_file() {
 
     assert!(fs::metadata(&path).is_ok(), "File is still there after dropping FileLock instance");
 }
+
+#[test]
+fn lock_guarded_reads_and_writes_through_the_guard() {
+    use std::io::{Read, Write, Seek, SeekFrom};
+
+    let tmp = TempFile::new("file-lock-guard-test", Mode::Write);
+    let path = tmp.path_buf();
+
+    {
+        let mut guard = FileLock::new(path.clone(), Mode::Write)
+                                 .lock_guarded(Kind::NonBlocking).unwrap();
+        guard.write_all(b"hello").unwrap();
+    }
+
+    let mut guard = FileLock::new(path, Mode::Read)
+                             .lock_guarded(Kind::NonBlocking).unwrap();
+    guard.seek(SeekFrom::Start(0)).unwrap();
+
+    let mut contents = String::new();
+    guard.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "hello");
+}
+
+#[test]
+fn upgrade_then_downgrade_round_trips_through_both_shared_and_exclusive() {
+    let tmp = TempFile::new("file-lock-upgrade-test", Mode::Write);
+
+    let mut fl = FileLock::new(tmp.path_buf(), Mode::Read);
+    fl.try_lock().unwrap();
+
+    fl.upgrade(Kind::NonBlocking).unwrap();
+    fl.downgrade().unwrap();
+
+    fl.unlock().unwrap();
+}
+
+#[test]
+fn upgrade_without_a_held_shared_lock_is_an_error() {
+    let tmp = TempFile::new("file-lock-upgrade-unlocked-test", Mode::Write);
+
+    let mut fl = FileLock::new(tmp.path_buf(), Mode::Read);
+    assert!(fl.upgrade(Kind::NonBlocking).is_err());
+}
+
+/// A minimal, executor-agnostic `block_on`, parking the thread between polls and relying on
+/// `LockFuture`'s own waker (a background thread calling `Waker::wake` after a short delay) to
+/// unpark it - there's no async runtime dependency to drive this with otherwise.
+fn block_on<F: std::future::Future>(mut fut: F) -> F::Output {
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread;
+    use std::time::Duration;
+
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut fut = unsafe { Pin::new_unchecked(&mut fut) };
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(val) => return val,
+            Poll::Pending => thread::park_timeout(Duration::from_millis(50)),
+        }
+    }
+}
+
+#[test]
+fn lock_async_completes_once_a_conflicting_exclusive_lock_is_released() {
+    use std::thread;
+    use std::time::Duration;
+
+    let tmp = TempFile::new("file-lock-async-test", Mode::Write);
+    let path = tmp.path_buf();
+
+    let mut holder = FileLock::new(path.clone(), Mode::Write);
+    holder.try_lock().unwrap();
+
+    let released = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(30));
+        holder.unlock().unwrap();
+    });
+
+    let guard = block_on(FileLock::new(path, Mode::Write).lock_async(Mode::Write)).unwrap();
+    drop(guard);
+
+    released.join().unwrap();
+}
+
+#[test]
+fn from_file_locks_an_already_open_handle() {
+    use std::os::unix::io::AsRawFd;
+    use std::fs::OpenOptions;
+
+    let tmp = TempFile::new("file-lock-from-file-test", Mode::Write);
+
+    let opened = OpenOptions::new().write(true).open(tmp.path()).unwrap();
+    let raw_fd = opened.as_raw_fd();
+
+    let mut fl = FileLock::from_file(opened, Mode::Write);
+    assert_eq!(fl.as_raw_fd(), raw_fd);
+
+    fl.try_lock().unwrap();
+    fl.unlock().unwrap();
+}